@@ -0,0 +1,172 @@
+//! Headless field-line tracer: reads a scene file (JSON or RON), traces E and/or B lines
+//! on the CPU with the same integrator as `ribbon_e_comp.wgsl` and writes the
+//! polylines as CSV, JSON, OBJ or legacy VTK PolyData. With `--grid` it
+//! samples the field on a regular grid instead and writes VTK ImageData.
+
+use anyhow::{Context, Result, bail};
+use em_viz_core::scene::{FieldLine, LineKind, Scene};
+use em_viz_core::scene_file::{SceneFile, Syntax};
+use em_viz_core::{export, gltf};
+use std::io::Write as _;
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+usage: em_viz-cli <scene> [-o <out>] [--format csv|json|obj|vtk|glb] [--field e|b|both]
+       em_viz-cli <scene> --grid <res> [-o <out>]
+
+Traces field lines for the charges in <scene> (a scene file saved by the web
+app, JSON or .ron, any version) and writes them to <out>
+(stdout when omitted). The format defaults to the extension of <out>, else csv.
+VTK line output carries |E| or |B|, V and arc length per point. glb writes
+the lines as coloured tubes and the charges as spheres.
+
+--grid samples E, V (and B if a charge moves) on <res>^3 nodes over a padded
+box around the charges and writes VTK structured points.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Csv,
+    Json,
+    Obj,
+    Vtk,
+    Glb,
+}
+
+impl Format {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            "obj" => Some(Self::Obj),
+            "vtk" => Some(Self::Vtk),
+            "glb" => Some(Self::Glb),
+            _ => None,
+        }
+    }
+}
+
+struct Args {
+    scene: PathBuf,
+    out: Option<PathBuf>,
+    format: Format,
+    kinds: Vec<LineKind>,
+    grid: Option<usize>,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut scene = None;
+    let mut out: Option<PathBuf> = None;
+    let mut format = None;
+    let mut kinds = vec![LineKind::E];
+    let mut grid = None;
+
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        let mut value = |flag: &str| it.next().with_context(|| format!("{flag} needs a value"));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            "-o" | "--out" => out = Some(value(&arg)?.into()),
+            "--format" => {
+                let v = value(&arg)?;
+                format = Some(Format::parse(&v).with_context(|| format!("unknown format `{v}`"))?);
+            }
+            "--field" => {
+                kinds = match value(&arg)?.as_str() {
+                    "e" | "E" => vec![LineKind::E],
+                    "b" | "B" => vec![LineKind::B],
+                    "both" => vec![LineKind::E, LineKind::B],
+                    v => bail!("unknown field `{v}`, expected e, b or both"),
+                }
+            }
+            "--grid" => {
+                let v = value(&arg)?;
+                let res = v.parse().ok().filter(|&r| r >= 2);
+                grid = Some(res.with_context(|| format!("--grid needs a size >= 2, got `{v}`"))?);
+            }
+            _ if arg.starts_with('-') => bail!("unknown option `{arg}`\n\n{USAGE}"),
+            _ if scene.is_none() => scene = Some(PathBuf::from(arg)),
+            _ => bail!("unexpected argument `{arg}`\n\n{USAGE}"),
+        }
+    }
+
+    let format = format
+        .or_else(|| {
+            let ext = out.as_ref()?.extension()?.to_str()?;
+            Format::parse(ext)
+        })
+        .unwrap_or(Format::Csv);
+    Ok(Args {
+        scene: scene.with_context(|| format!("missing scene file\n\n{USAGE}"))?,
+        out,
+        format,
+        kinds,
+        grid,
+    })
+}
+
+fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    let text = std::fs::read_to_string(&args.scene)
+        .with_context(|| format!("reading {}", args.scene.display()))?;
+    let syntax = Syntax::from_name(&args.scene.to_string_lossy());
+    let file = SceneFile::parse(&text, syntax)
+        .map_err(anyhow::Error::msg)
+        .with_context(|| format!("parsing {}", args.scene.display()))?;
+    let scene = file.scene();
+
+    let (body, summary) = match args.grid {
+        Some(res) => {
+            if args.format != Format::Vtk && args.out.is_some() {
+                eprintln!("note: --grid always writes VTK");
+            }
+            let grid = scene.sample_grid(res);
+            let summary = format!("sampled {} grid nodes", grid.len());
+            (
+                export::grid_to_vtk(&grid, "em_viz field samples").into_bytes(),
+                summary,
+            )
+        }
+        None => {
+            let lines = trace_lines(&scene, &args.kinds);
+            let body = match args.format {
+                Format::Csv => export::lines_to_csv(&lines).into_bytes(),
+                Format::Json => export::lines_to_json(&lines).into_bytes(),
+                Format::Obj => export::lines_to_obj(&lines).into_bytes(),
+                Format::Vtk => {
+                    export::lines_to_vtk(&lines, |p| scene.potential(p), "em_viz field lines")
+                        .into_bytes()
+                }
+                Format::Glb => gltf::scene_glb(&scene.charges, &lines, file.display.colormaps),
+            };
+            let points: usize = lines.iter().map(|l| l.points.len()).sum();
+            (
+                body,
+                format!("traced {} lines, {points} points", lines.len()),
+            )
+        }
+    };
+    match &args.out {
+        Some(path) => {
+            std::fs::write(path, body).with_context(|| format!("writing {}", path.display()))?
+        }
+        None => match std::io::stdout().write_all(&body) {
+            // the reader quit early (`| head`); that is all it wanted
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => return Ok(()),
+            r => r.context("writing stdout")?,
+        },
+    }
+
+    eprintln!("{summary}");
+    Ok(())
+}
+
+fn trace_lines(scene: &Scene, kinds: &[LineKind]) -> Vec<FieldLine> {
+    if kinds.contains(&LineKind::B) && scene.charges.iter().all(|c| c.vel == Default::default()) {
+        eprintln!("note: no charge has a velocity, so there are no B lines");
+    }
+    kinds.iter().flat_map(|&k| scene.trace(k)).collect()
+}
@@ -0,0 +1,74 @@
+//! Runs the built binary on `examples/dipole.json`.
+
+use em_viz_core::scene::LineKind;
+use em_viz_core::scene_file::{SceneFile, Syntax};
+use std::io::Read as _;
+use std::process::{Command, Stdio};
+
+const EXAMPLE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples/dipole.json");
+
+fn cli() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_em_viz-cli"))
+}
+
+#[test]
+fn writes_one_csv_row_per_traced_point() {
+    let out = cli().args([EXAMPLE, "--format", "csv"]).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let csv = String::from_utf8(out.stdout).unwrap();
+    let mut rows = csv.lines();
+    assert_eq!(rows.next(), Some("line,kind,point,x,y,z,magnitude"));
+
+    let text = std::fs::read_to_string(EXAMPLE).unwrap();
+    let scene = SceneFile::parse(&text, Syntax::Json).unwrap().scene();
+    let points: usize = scene
+        .trace(LineKind::E)
+        .iter()
+        .map(|l| l.points.len())
+        .sum();
+    assert!(points > 0);
+    assert_eq!(rows.count(), points);
+}
+
+#[test]
+fn writes_vtk_grids() {
+    let out = cli().args([EXAMPLE, "--grid", "4"]).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let vtk = String::from_utf8(out.stdout).unwrap();
+    assert!(
+        vtk.starts_with("# vtk DataFile"),
+        "{}",
+        &vtk[..40.min(vtk.len())]
+    );
+    assert!(vtk.contains("DIMENSIONS 4 4 4"));
+}
+
+#[test]
+fn a_reader_that_stops_early_is_not_an_error() {
+    // far more than a pipe buffer, so the write outlives the reader
+    let mut child = cli()
+        .args([EXAMPLE, "--grid", "48"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut first = [0; 16];
+    child.stdout.take().unwrap().read_exact(&mut first).unwrap();
+    let status = child.wait().unwrap();
+    let mut err = String::new();
+    child
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut err)
+        .unwrap();
+    assert!(status.success(), "{err}");
+}
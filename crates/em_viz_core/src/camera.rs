@@ -0,0 +1,53 @@
+use glam::{Mat4, Vec3};
+
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub fov_y: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+impl Camera {
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            eye: Vec3::new(0.0, 0.0, 0.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            fov_y: 50f32.to_radians(),
+            aspect,
+            near: 0.01,
+            far: 250.0,
+        }
+    }
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
+    pub fn proj(&self) -> Mat4 {
+        Mat4::perspective_rh_gl(self.fov_y, self.aspect, self.near, self.far)
+    }
+    pub fn update_from_orbit(&mut self, o: &Orbit) {
+        self.target = o.target;
+        self.eye = orbit_eye(o.target, o.yaw, o.pitch, o.radius);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Orbit {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub radius: f32,
+    pub target: Vec3,
+}
+
+pub fn orbit_eye(target: Vec3, yaw: f32, pitch: f32, radius: f32) -> Vec3 {
+    let cp = pitch.cos();
+    let sp = pitch.sin();
+    let cy = yaw.cos();
+    let sy = yaw.sin();
+    let dir = Vec3::new(cy * cp, sp, sy * cp);
+    target + radius * dir
+}
@@ -0,0 +1,102 @@
+//! The ribbon palettes from `ribbon3d.frag.glsl`, for exports that bake colours
+//! into geometry, and the choice of palette per line layer.
+
+use crate::scene::LineKind;
+
+const VIRIDIS: [[f32; 3]; 6] = [
+    [0.267, 0.005, 0.329],
+    [0.283, 0.141, 0.458],
+    [0.254, 0.266, 0.530],
+    [0.207, 0.372, 0.553],
+    [0.164, 0.471, 0.558],
+    [0.993, 0.906, 0.144],
+];
+
+// coarse 6-stop plasma
+const PLASMA: [[f32; 3]; 6] = [
+    [0.050, 0.030, 0.527],
+    [0.302, 0.005, 0.705],
+    [0.553, 0.053, 0.741],
+    [0.792, 0.227, 0.643],
+    [0.956, 0.427, 0.397],
+    [0.987, 0.772, 0.258],
+];
+
+fn ramp(stops: &[[f32; 3]; 6], t: f32) -> [f32; 3] {
+    let x = t.clamp(0.0, 1.0) * 5.0;
+    let i = (x.floor() as usize).min(4);
+    let f = x - i as f32;
+    let (a, b) = (stops[i], stops[i + 1]);
+    [0, 1, 2].map(|c| a[c] + (b[c] - a[c]) * f)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Colormap {
+    Viridis,
+    Plasma,
+}
+
+impl Colormap {
+    pub const ALL: [Self; 2] = [Self::Viridis, Self::Plasma];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Viridis => "Viridis",
+            Self::Plasma => "Plasma",
+        }
+    }
+
+    /// Stable identifier for UIs and files.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Viridis => "viridis",
+            Self::Plasma => "plasma",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.id() == id)
+    }
+
+    /// The shaders' palette number (`u_palette`).
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    /// RGB (0..1) at palette coordinate `t`.
+    pub fn color(self, t: f32) -> [f32; 3] {
+        match self {
+            Self::Viridis => ramp(&VIRIDIS, t),
+            Self::Plasma => ramp(&PLASMA, t),
+        }
+    }
+}
+
+/// The palette of each line layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Colormaps {
+    pub e: Colormap,
+    pub b: Colormap,
+}
+
+impl Default for Colormaps {
+    fn default() -> Self {
+        Self {
+            e: Colormap::Viridis,
+            b: Colormap::Plasma,
+        }
+    }
+}
+
+impl Colormaps {
+    pub fn of(self, kind: LineKind) -> Colormap {
+        match kind {
+            LineKind::E => self.e,
+            LineKind::B => self.b,
+        }
+    }
+}
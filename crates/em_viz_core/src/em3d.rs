@@ -0,0 +1,107 @@
+use glam::Vec3;
+
+pub mod multipole;
+pub mod octree;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Charge3D {
+    pub pos: Vec3,
+    pub q: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub vel: Vec3, // NEW: velocity in world units / s (visual units)
+}
+
+pub fn sample_e3d(p: Vec3, charges: &[Charge3D], k: f32, soft2: f32) -> Vec3 {
+    let mut e = Vec3::ZERO;
+    for c in charges {
+        let d = p - c.pos;
+        let r2 = d.length_squared() + soft2;
+        let r = r2.sqrt();
+        e += (k * c.q / (r2 * r)) * d; // d / r^3
+    }
+    e
+}
+
+/// Electrostatic potential with the same softening as [`sample_e3d`].
+pub fn sample_v3d(p: Vec3, charges: &[Charge3D], k: f32, soft2: f32) -> f32 {
+    charges
+        .iter()
+        .map(|c| k * c.q / ((p - c.pos).length_squared() + soft2).sqrt())
+        .sum()
+}
+
+// Helper: field of a single charge (for B approximation)
+fn sample_e_of_charge(p: Vec3, c: &Charge3D, k: f32, soft2: f32) -> Vec3 {
+    let d = p - c.pos;
+    let r2 = d.length_squared() + soft2;
+    let r = r2.sqrt();
+    (k * c.q / (r2 * r)) * d
+}
+
+/// Approx magnetic field (non-retarded, low-v): B(x) ≈ (1/c^2) Σ [ v_i × E_i(x) ]
+pub fn sample_b3d(p: Vec3, charges: &[Charge3D], k: f32, soft2: f32, c_inv2: f32) -> Vec3 {
+    let mut b = Vec3::ZERO;
+    for c in charges {
+        if c.vel.length_squared() < 1e-10 {
+            continue;
+        }
+        let ei = sample_e_of_charge(p, c, k, soft2);
+        b += c_inv2 * c.vel.cross(ei);
+    }
+    b
+}
+
+/// Same sum as [`sample_e3d`], walked in fixed-size tiles of charges. Mirrors the
+/// workgroup-shared-memory path in `ribbon_e_comp.wgsl` (`main_tiled`).
+pub fn sample_e3d_tiled(p: Vec3, charges: &[Charge3D], k: f32, soft2: f32, tile: usize) -> Vec3 {
+    let mut e = Vec3::ZERO;
+    for chunk in charges.chunks(tile.max(1)) {
+        let mut e_tile = Vec3::ZERO;
+        for c in chunk {
+            e_tile += sample_e_of_charge(p, c, k, soft2);
+        }
+        e += e_tile;
+    }
+    e
+}
+
+/// How the field of many charges is evaluated at a sample point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum FieldEval {
+    /// Exact superposition over every charge, O(N) per sample.
+    Direct,
+    /// Exact superposition in tiles of charges (shared memory on the GPU).
+    Tiled,
+    /// Octree approximation: nodes with radius / distance < `theta` are treated
+    /// as a monopole + dipole, see [`octree::Octree::sample_e`].
+    BarnesHut { theta: f32 },
+}
+
+impl FieldEval {
+    pub const DEFAULT_THETA: f32 = 0.5;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiled_sum_matches_direct_sum() {
+        let charges: Vec<Charge3D> = (0..150)
+            .map(|i| Charge3D {
+                pos: Vec3::new((i % 7) as f32, (i % 5) as f32, (i % 3) as f32) * 0.3,
+                q: if i % 3 == 0 { -1.0 } else { 0.5 },
+                vel: Vec3::ZERO,
+            })
+            .collect();
+        let p = Vec3::new(0.11, -0.7, 0.35);
+        let direct = sample_e3d(p, &charges, 1.0, 0.0025);
+        for tile in [1, 64, 1000] {
+            let tiled = sample_e3d_tiled(p, &charges, 1.0, 0.0025, tile);
+            assert!((direct - tiled).length() <= 1e-4 * direct.length());
+        }
+    }
+}
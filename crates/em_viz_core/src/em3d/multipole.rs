@@ -0,0 +1,318 @@
+//! Cartesian treecode: octree cells carry multipole moments up to a configurable
+//! order and far cells are evaluated from their Taylor expansion instead of the
+//! direct sum. Runs in f64; the results are rounded back to f32 like the rest of
+//! `em3d`.
+//!
+//! With `R = x - c` (target minus cell centre) and `d_j = x_j - c`,
+//!
+//!   φ(x) = Σ_k (-1)^|k| M_k a_k(R),   M_k = Σ_j q_j d_j^k,   a_k = ∂^k (1/s) / k!
+//!
+//! where `s² = |R|² + soft2`. The Taylor coefficients satisfy
+//!
+//!   |k| s² a_k = -(2|k| - 1) Σ_i R_i a_{k-e_i} - (|k| - 1) Σ_i a_{k-2e_i}
+//!
+//! and the field follows from `∂_i a_k = (k_i + 1) a_{k+e_i}`.
+
+use super::Charge3D;
+use super::octree::Octree;
+use glam::{DVec3, Vec3};
+
+/// E and V at one point, with error estimates for the truncated expansions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FieldSample {
+    pub e: Vec3,
+    pub v: f32,
+    /// Upper bound on |V - V_exact| (strict for `soft2 = 0`; softening only
+    /// shrinks the error).
+    pub v_err_bound: f32,
+    /// Estimate of |E - E_exact| with the same (r/R)^(p+1) decay as the bound on V.
+    pub e_err_est: f32,
+}
+
+pub struct Treecode {
+    tree: Octree,
+    order: usize,
+    terms: Vec<[usize; 3]>, // multi-indices with |k| <= order
+    moments: Vec<f64>,      // terms.len() per node
+    abs_q: Vec<f64>,        // Σ|q| per node, for the error bound
+}
+
+impl Treecode {
+    pub const DEFAULT_ORDER: usize = 4;
+
+    pub fn build(charges: &[Charge3D], order: usize, leaf_size: usize) -> Self {
+        let tree = Octree::build(charges, leaf_size);
+        let terms = multi_indices(order);
+        let mut moments = vec![0.0; terms.len() * tree.nodes.len()];
+        let mut abs_q = Vec::with_capacity(tree.nodes.len());
+
+        let mut pows = Powers::new(order);
+        for (ni, node) in tree.nodes.iter().enumerate() {
+            let c = node.center.as_dvec3();
+            let range = node.start as usize..(node.start + node.count) as usize;
+            let m = &mut moments[ni * terms.len()..(ni + 1) * terms.len()];
+            for ch in &tree.charges[range.clone()] {
+                pows.fill(ch.pos.as_dvec3() - c);
+                let q = ch.q as f64;
+                for (mk, k) in m.iter_mut().zip(&terms) {
+                    *mk += q * pows.get(*k);
+                }
+            }
+            abs_q.push(tree.charges[range].iter().map(|ch| ch.q.abs() as f64).sum());
+        }
+
+        Self {
+            tree,
+            order,
+            terms,
+            moments,
+            abs_q,
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// E and V at `p`. Cells with `radius < theta * distance` (theta < 1) use
+    /// their expansion; opened leaves are summed directly.
+    pub fn eval(&self, p: Vec3, k: f32, soft2: f32, theta: f32) -> FieldSample {
+        let mut scratch = Taylor::new(self.order + 1);
+        self.eval_with(
+            &mut scratch,
+            p,
+            k as f64,
+            soft2 as f64,
+            theta.min(0.999) as f64,
+        )
+    }
+
+    /// [`Treecode::eval`] over many points, sharing one scratch buffer.
+    pub fn eval_batch(&self, points: &[Vec3], k: f32, soft2: f32, theta: f32) -> Vec<FieldSample> {
+        let mut scratch = Taylor::new(self.order + 1);
+        let theta = theta.min(0.999) as f64;
+        points
+            .iter()
+            .map(|&p| self.eval_with(&mut scratch, p, k as f64, soft2 as f64, theta))
+            .collect()
+    }
+
+    fn eval_with(&self, t: &mut Taylor, p: Vec3, k: f64, soft2: f64, theta: f64) -> FieldSample {
+        let x = p.as_dvec3();
+        let nt = self.terms.len();
+        let (mut e, mut v, mut v_err, mut e_err) = (DVec3::ZERO, 0.0, 0.0, 0.0);
+
+        let mut i = 0;
+        while i < self.tree.nodes.len() {
+            let nd = &self.tree.nodes[i];
+            let rr = x - nd.center.as_dvec3();
+            let dist = rr.length();
+            let r = nd.radius as f64;
+            if r < theta * dist {
+                t.fill(rr, soft2);
+                let m = &self.moments[i * nt..(i + 1) * nt];
+                for (&mk, &kk) in m.iter().zip(&self.terms) {
+                    let sign = if (kk[0] + kk[1] + kk[2]) % 2 == 0 {
+                        1.0
+                    } else {
+                        -1.0
+                    };
+                    let w = sign * mk;
+                    v += w * t.get(kk);
+                    e -= w * DVec3::new(
+                        (kk[0] + 1) as f64 * t.get([kk[0] + 1, kk[1], kk[2]]),
+                        (kk[1] + 1) as f64 * t.get([kk[0], kk[1] + 1, kk[2]]),
+                        (kk[2] + 1) as f64 * t.get([kk[0], kk[1], kk[2] + 1]),
+                    );
+                }
+                let ratio = (r / dist).powi(self.order as i32 + 1);
+                v_err += self.abs_q[i] * ratio / (dist - r);
+                e_err += self.abs_q[i] * ratio * (self.order as f64 + 2.0) / (dist * (dist - r));
+                i = nd.next as usize;
+            } else if nd.leaf {
+                let range = nd.start as usize..(nd.start + nd.count) as usize;
+                for ch in &self.tree.charges[range] {
+                    let d = x - ch.pos.as_dvec3();
+                    let s2 = d.length_squared() + soft2;
+                    let s = s2.sqrt();
+                    v += ch.q as f64 / s;
+                    e += (ch.q as f64 / (s2 * s)) * d;
+                }
+                i = nd.next as usize;
+            } else {
+                i += 1;
+            }
+        }
+
+        FieldSample {
+            e: (k * e).as_vec3(),
+            v: (k * v) as f32,
+            v_err_bound: (k * v_err) as f32,
+            e_err_est: (k * e_err) as f32,
+        }
+    }
+}
+
+fn multi_indices(order: usize) -> Vec<[usize; 3]> {
+    let mut out = Vec::new();
+    for n in 0..=order {
+        for i in (0..=n).rev() {
+            for j in (0..=n - i).rev() {
+                out.push([i, j, n - i - j]);
+            }
+        }
+    }
+    out
+}
+
+/// Dense table of d^k = dx^i dy^j dz^l for |k| <= order.
+struct Powers {
+    dim: usize,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    z: Vec<f64>,
+}
+
+impl Powers {
+    fn new(order: usize) -> Self {
+        let dim = order + 1;
+        Self {
+            dim,
+            x: vec![1.0; dim],
+            y: vec![1.0; dim],
+            z: vec![1.0; dim],
+        }
+    }
+    fn fill(&mut self, d: DVec3) {
+        for n in 1..self.dim {
+            self.x[n] = self.x[n - 1] * d.x;
+            self.y[n] = self.y[n - 1] * d.y;
+            self.z[n] = self.z[n - 1] * d.z;
+        }
+    }
+    fn get(&self, [i, j, l]: [usize; 3]) -> f64 {
+        self.x[i] * self.y[j] * self.z[l]
+    }
+}
+
+/// Taylor coefficients a_k of the softened kernel 1/s for |k| <= max_order.
+struct Taylor {
+    dim: usize,
+    a: Vec<f64>,
+}
+
+impl Taylor {
+    fn new(max_order: usize) -> Self {
+        let dim = max_order + 1;
+        Self {
+            dim,
+            a: vec![0.0; dim * dim * dim],
+        }
+    }
+    fn at(&self, [i, j, l]: [usize; 3]) -> usize {
+        (i * self.dim + j) * self.dim + l
+    }
+    fn get(&self, k: [usize; 3]) -> f64 {
+        self.a[self.at(k)]
+    }
+    fn fill(&mut self, r: DVec3, soft2: f64) {
+        let s2 = r.length_squared() + soft2;
+        let a0 = 1.0 / s2.sqrt();
+        let idx0 = self.at([0, 0, 0]);
+        self.a[idx0] = a0;
+        let rc = [r.x, r.y, r.z];
+        for n in 1..self.dim {
+            for i in (0..=n).rev() {
+                for j in (0..=n - i).rev() {
+                    let k = [i, j, n - i - j];
+                    let mut s1 = 0.0;
+                    let mut s2m = 0.0;
+                    for ax in 0..3 {
+                        if k[ax] >= 1 {
+                            let mut km = k;
+                            km[ax] -= 1;
+                            s1 += rc[ax] * self.get(km);
+                        }
+                        if k[ax] >= 2 {
+                            let mut km = k;
+                            km[ax] -= 2;
+                            s2m += self.get(km);
+                        }
+                    }
+                    let nf = n as f64;
+                    let idx = self.at(k);
+                    self.a[idx] = -((2.0 * nf - 1.0) * s1 + (nf - 1.0) * s2m) / (nf * s2);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::em3d::{sample_e3d, sample_v3d};
+
+    fn cloud(n: usize) -> Vec<Charge3D> {
+        let mut s = 0x9e37_79b9_u32;
+        let mut r = move || {
+            s = s.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (s >> 8) as f32 / (1u32 << 24) as f32
+        };
+        (0..n)
+            .map(|_| Charge3D {
+                pos: Vec3::new(r(), r(), r()) * 2.0 - 1.0,
+                q: r() * 2.0 - 0.7,
+                vel: Vec3::ZERO,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_direct_sum_within_bound() {
+        let cs = cloud(3000);
+        let points: Vec<Vec3> = (0..40)
+            .map(|i| {
+                let a = i as f32 * 0.7;
+                Vec3::new(a.cos(), (0.3 * a).sin(), a.sin()) * (0.5 + 0.1 * i as f32)
+            })
+            .collect();
+        let tc = Treecode::build(&cs, 6, Octree::DEFAULT_LEAF_SIZE);
+        for (p, s) in points.iter().zip(tc.eval_batch(&points, 1.0, 0.0, 0.5)) {
+            let v = sample_v3d(*p, &cs, 1.0, 0.0);
+            let e = sample_e3d(*p, &cs, 1.0, 0.0);
+            // allow for the f32 rounding of the direct sums
+            let v_tol = s.v_err_bound + 1e-4 * v.abs().max(1.0);
+            assert!(
+                (s.v - v).abs() <= v_tol,
+                "V {} vs {} (bound {})",
+                s.v,
+                v,
+                s.v_err_bound
+            );
+            let e_tol = s.e_err_est + 1e-4 * e.length().max(1.0);
+            assert!((s.e - e).length() <= e_tol, "E {} vs {}", s.e, e);
+        }
+    }
+
+    #[test]
+    fn error_drops_with_order() {
+        let cs = cloud(2000);
+        let p = Vec3::new(1.7, -0.4, 0.9);
+        let e = sample_e3d(p, &cs, 1.0, 0.0025);
+        let err = |order| {
+            let s =
+                Treecode::build(&cs, order, Octree::DEFAULT_LEAF_SIZE).eval(p, 1.0, 0.0025, 0.7);
+            let abs = (s.e - e).length();
+            assert!(
+                abs <= s.e_err_est + 1e-5,
+                "order {order}: {abs} > est {}",
+                s.e_err_est
+            );
+            abs / e.length()
+        };
+        let (e1, e3, e6) = (err(1), err(3), err(6));
+        assert!(e3 < e1 && e6 < e3, "{e1} {e3} {e6}");
+        assert!(e6 < 2e-3, "order 6 error {e6}");
+    }
+}
@@ -0,0 +1,212 @@
+use super::{Charge3D, sample_e3d};
+use glam::Vec3;
+
+/// One octree cell. Nodes are stored in depth-first preorder, so the first child
+/// of a node (if any) is the next entry and `next` skips the whole subtree. That
+/// keeps traversal stackless, which is what the WGSL side (`sample_e_bh`) needs.
+#[derive(Clone, Copy, Debug)]
+pub struct OctreeNode {
+    pub center: Vec3, // expansion centre: |q|-weighted centroid of the cell's charges
+    pub radius: f32,  // max distance from `center` to any charge in the cell
+    pub q: f32,       // monopole: total charge
+    pub dipole: Vec3, // Σ q (x - center)
+    pub start: u32,   // range into `Octree::charges`
+    pub count: u32,
+    pub next: u32, // preorder index of the node after this subtree
+    pub leaf: bool,
+}
+
+impl OctreeNode {
+    /// Monopole + dipole field at offset `d = p - center`.
+    pub fn far_field(&self, d: Vec3, k: f32, soft2: f32) -> Vec3 {
+        let s2 = d.length_squared() + soft2;
+        let inv = 1.0 / s2.sqrt();
+        let inv3 = inv * inv * inv;
+        let inv5 = inv3 * inv * inv;
+        k * (self.q * inv3 * d + 3.0 * self.dipole.dot(d) * inv5 * d - inv3 * self.dipole)
+    }
+}
+
+/// Barnes–Hut octree over a set of charges.
+#[derive(Clone, Debug, Default)]
+pub struct Octree {
+    pub nodes: Vec<OctreeNode>,
+    pub charges: Vec<Charge3D>, // reordered so every node covers a contiguous range
+}
+
+impl Octree {
+    pub const DEFAULT_LEAF_SIZE: usize = 8;
+    // stops runaway subdivision when charges coincide
+    const MAX_DEPTH: u32 = 20;
+
+    pub fn build(charges: &[Charge3D], leaf_size: usize) -> Self {
+        let mut tree = Self {
+            nodes: Vec::with_capacity(2 * charges.len() / leaf_size.max(1) + 1),
+            charges: charges.to_vec(),
+        };
+        if charges.is_empty() {
+            return tree;
+        }
+        let (lo, hi) = charges.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(lo, hi), c| (lo.min(c.pos), hi.max(c.pos)),
+        );
+        let center = 0.5 * (lo + hi);
+        let half = (0.5 * (hi - lo)).max_element().max(1e-6);
+        tree.build_node(0, charges.len(), center, half, leaf_size.max(1), 0);
+        tree
+    }
+
+    fn build_node(
+        &mut self,
+        start: usize,
+        end: usize,
+        box_center: Vec3,
+        half: f32,
+        leaf_size: usize,
+        depth: u32,
+    ) {
+        let idx = self.nodes.len();
+        let leaf = end - start <= leaf_size || depth >= Self::MAX_DEPTH;
+        self.nodes
+            .push(self.summarize(start, end, box_center, leaf));
+
+        if !leaf {
+            let octant = |p: Vec3| {
+                (p.x >= box_center.x) as usize
+                    | ((p.y >= box_center.y) as usize) << 1
+                    | ((p.z >= box_center.z) as usize) << 2
+            };
+            self.charges[start..end].sort_by_key(|c| octant(c.pos));
+
+            let mut s = start;
+            for o in 0..8 {
+                let mut e = s;
+                while e < end && octant(self.charges[e].pos) == o {
+                    e += 1;
+                }
+                if e > s {
+                    let sign = |bit: usize| if o & bit != 0 { 0.5 } else { -0.5 };
+                    let child_center = box_center + half * Vec3::new(sign(1), sign(2), sign(4));
+                    self.build_node(s, e, child_center, 0.5 * half, leaf_size, depth + 1);
+                }
+                s = e;
+            }
+        }
+
+        self.nodes[idx].next = self.nodes.len() as u32;
+    }
+
+    fn summarize(&self, start: usize, end: usize, box_center: Vec3, leaf: bool) -> OctreeNode {
+        let cs = &self.charges[start..end];
+        let w: f32 = cs.iter().map(|c| c.q.abs()).sum();
+        let center = if w > 0.0 {
+            cs.iter().map(|c| c.q.abs() * c.pos).sum::<Vec3>() / w
+        } else {
+            box_center
+        };
+        OctreeNode {
+            center,
+            radius: cs
+                .iter()
+                .map(|c| c.pos.distance(center))
+                .fold(0.0, f32::max),
+            q: cs.iter().map(|c| c.q).sum(),
+            dipole: cs.iter().map(|c| c.q * (c.pos - center)).sum(),
+            start: start as u32,
+            count: (end - start) as u32,
+            next: 0, // patched once the subtree is built
+            leaf,
+        }
+    }
+
+    /// Approximate E at `p`. Cells with `radius < theta * distance` use their
+    /// multipole; opened leaves are summed directly. `theta = 0` is exact.
+    pub fn sample_e(&self, p: Vec3, k: f32, soft2: f32, theta: f32) -> Vec3 {
+        let theta2 = theta * theta;
+        let mut e = Vec3::ZERO;
+        let mut i = 0;
+        while i < self.nodes.len() {
+            let nd = &self.nodes[i];
+            let d = p - nd.center;
+            if nd.radius * nd.radius < theta2 * d.length_squared() {
+                e += nd.far_field(d, k, soft2);
+                i = nd.next as usize;
+            } else if nd.leaf {
+                let range = nd.start as usize..(nd.start + nd.count) as usize;
+                e += sample_e3d(p, &self.charges[range], k, soft2);
+                i = nd.next as usize;
+            } else {
+                i += 1;
+            }
+        }
+        e
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // small deterministic LCG so the tests don't need a rand dependency
+    fn lattice(n: usize) -> Vec<Charge3D> {
+        let mut s = 0x2545_f491_u32;
+        let mut r = move || {
+            s = s.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (s >> 8) as f32 / (1u32 << 24) as f32
+        };
+        (0..n)
+            .map(|i| Charge3D {
+                pos: Vec3::new(r(), r(), r()) * 2.0 - 1.0,
+                q: if i % 2 == 0 { 1.0 } else { -0.8 },
+                vel: Vec3::ZERO,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tree_partitions_every_charge_once() {
+        let cs = lattice(500);
+        let tree = Octree::build(&cs, 4);
+        let leaf_total: u32 = tree.nodes.iter().filter(|n| n.leaf).map(|n| n.count).sum();
+        assert_eq!(leaf_total, 500);
+        let q: f32 = cs.iter().map(|c| c.q).sum();
+        assert!((tree.nodes[0].q - q).abs() < 1e-3);
+        assert_eq!(tree.nodes[0].next as usize, tree.nodes.len());
+    }
+
+    #[test]
+    fn theta_zero_matches_direct_sum() {
+        let cs = lattice(300);
+        let tree = Octree::build(&cs, Octree::DEFAULT_LEAF_SIZE);
+        for p in [Vec3::new(0.1, 0.2, 0.3), Vec3::new(2.0, -1.0, 0.5)] {
+            let exact = sample_e3d(p, &cs, 1.0, 0.0025);
+            let bh = tree.sample_e(p, 1.0, 0.0025, 0.0);
+            assert!((exact - bh).length() <= 1e-4 * exact.length().max(1.0));
+        }
+    }
+
+    #[test]
+    fn barnes_hut_error_shrinks_with_theta() {
+        let cs = lattice(2000);
+        let tree = Octree::build(&cs, Octree::DEFAULT_LEAF_SIZE);
+        let probes = [
+            Vec3::new(3.0, 0.5, -0.2),
+            Vec3::new(-0.4, 2.5, 1.5),
+            Vec3::new(0.05, -0.1, 0.2),
+        ];
+        let max_rel_err = |theta: f32| {
+            probes
+                .iter()
+                .map(|&p| {
+                    let exact = sample_e3d(p, &cs, 1.0, 0.0025);
+                    (tree.sample_e(p, 1.0, 0.0025, theta) - exact).length() / exact.length()
+                })
+                .fold(0.0, f32::max)
+        };
+        let coarse = max_rel_err(0.8);
+        let fine = max_rel_err(0.1);
+        assert!(fine < 5e-3, "theta 0.1 error {fine}");
+        assert!(fine < coarse);
+    }
+}
@@ -0,0 +1,239 @@
+//! Plain-text writers for traced field lines. Each returns the whole file as a
+//! `String` so the same code serves the CLI (written to disk) and the web app
+//! (offered as a download).
+
+use crate::grid::FieldSamples;
+use crate::scene::FieldLine;
+use glam::Vec3;
+use std::fmt::Write as _;
+
+/// `line,kind,point,x,y,z,magnitude`, one row per point.
+pub fn lines_to_csv(lines: &[FieldLine]) -> String {
+    let mut s = String::from("line,kind,point,x,y,z,magnitude\n");
+    for (li, line) in lines.iter().enumerate() {
+        for (pi, p) in line.points.iter().enumerate() {
+            let _ = writeln!(
+                s,
+                "{li},{},{pi},{},{},{},{}",
+                line.kind.name(),
+                p.pos.x,
+                p.pos.y,
+                p.pos.z,
+                p.mag
+            );
+        }
+    }
+    s
+}
+
+/// `{"lines": [{"kind": "E", "points": [[x, y, z], ...], "magnitude": [...]}]}`
+pub fn lines_to_json(lines: &[FieldLine]) -> String {
+    let mut s = String::from("{\"lines\":[");
+    for (li, line) in lines.iter().enumerate() {
+        if li > 0 {
+            s.push(',');
+        }
+        let _ = write!(s, "{{\"kind\":\"{}\",\"points\":[", line.kind.name());
+        for (pi, p) in line.points.iter().enumerate() {
+            let sep = if pi > 0 { "," } else { "" };
+            let _ = write!(s, "{sep}[{},{},{}]", p.pos.x, p.pos.y, p.pos.z);
+        }
+        s.push_str("],\"magnitude\":[");
+        for (pi, p) in line.points.iter().enumerate() {
+            let sep = if pi > 0 { "," } else { "" };
+            let _ = write!(s, "{sep}{}", p.mag);
+        }
+        s.push_str("]}");
+    }
+    s.push_str("]}\n");
+    s
+}
+
+/// Wavefront OBJ with one `l` polyline per field line, grouped by kind.
+pub fn lines_to_obj(lines: &[FieldLine]) -> String {
+    let mut s = String::from("# em_viz field lines\n");
+    let mut next = 1; // OBJ indices are 1-based
+    for line in lines {
+        if line.points.len() < 2 {
+            continue;
+        }
+        let _ = writeln!(s, "g {}_lines", line.kind.name());
+        for p in &line.points {
+            let _ = writeln!(s, "v {} {} {}", p.pos.x, p.pos.y, p.pos.z);
+        }
+        s.push('l');
+        for i in next..next + line.points.len() {
+            let _ = write!(s, " {i}");
+        }
+        s.push('\n');
+        next += line.points.len();
+    }
+    s
+}
+
+/// Legacy ASCII VTK PolyData for ParaView: one polyline per field line. Point
+/// data holds the field magnitude (|E| or |B| depending on the line), the
+/// potential V from `potential` and the arc length from the line's seed; cell
+/// data holds the line kind (0 = E, 1 = B).
+pub fn lines_to_vtk(lines: &[FieldLine], potential: impl Fn(Vec3) -> f32, title: &str) -> String {
+    let n: usize = lines.iter().map(|l| l.points.len()).sum();
+    let points = || lines.iter().flat_map(|l| &l.points);
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "# vtk DataFile Version 3.0\n{title}\nASCII\nDATASET POLYDATA"
+    );
+    let _ = writeln!(s, "POINTS {n} float");
+    for p in points() {
+        let _ = writeln!(s, "{} {} {}", p.pos.x, p.pos.y, p.pos.z);
+    }
+
+    let size: usize = lines.iter().map(|l| l.points.len() + 1).sum();
+    let _ = writeln!(s, "LINES {} {size}", lines.len());
+    let mut next = 0;
+    for line in lines {
+        let _ = write!(s, "{}", line.points.len());
+        for i in next..next + line.points.len() {
+            let _ = write!(s, " {i}");
+        }
+        s.push('\n');
+        next += line.points.len();
+    }
+
+    let _ = writeln!(
+        s,
+        "CELL_DATA {}\nSCALARS kind int 1\nLOOKUP_TABLE default",
+        lines.len()
+    );
+    for line in lines {
+        let _ = writeln!(s, "{}", line.kind as u8);
+    }
+
+    let _ = writeln!(s, "POINT_DATA {n}");
+    scalars(&mut s, "magnitude", points().map(|p| p.mag));
+    scalars(&mut s, "V", points().map(|p| potential(p.pos)));
+    let arc = lines.iter().flat_map(|l| {
+        l.points
+            .iter()
+            .scan((0.0, l.points.first().map(|p| p.pos)), |(len, prev), p| {
+                *len += prev.map_or(0.0, |q| p.pos.distance(q));
+                *prev = Some(p.pos);
+                Some(*len)
+            })
+    });
+    scalars(&mut s, "arc_length", arc);
+    s
+}
+
+/// Legacy ASCII VTK STRUCTURED_POINTS (ImageData) of sampled fields: vectors E
+/// (and B when sampled), scalars V and |E|.
+pub fn grid_to_vtk(grid: &FieldSamples, title: &str) -> String {
+    let [nx, ny, nz] = grid.dims;
+    let (o, d) = (grid.origin, grid.spacing);
+    let mut s = String::new();
+    let _ = writeln!(
+        s,
+        "# vtk DataFile Version 3.0\n{title}\nASCII\nDATASET STRUCTURED_POINTS"
+    );
+    let _ = writeln!(s, "DIMENSIONS {nx} {ny} {nz}");
+    let _ = writeln!(s, "ORIGIN {} {} {}", o.x, o.y, o.z);
+    let _ = writeln!(s, "SPACING {} {} {}", d.x, d.y, d.z);
+    let _ = writeln!(s, "POINT_DATA {}", grid.len());
+    vectors(&mut s, "E", &grid.e);
+    if let Some(b) = &grid.b {
+        vectors(&mut s, "B", b);
+    }
+    scalars(&mut s, "V", grid.v.iter().copied());
+    scalars(&mut s, "E_magnitude", grid.e.iter().map(|e| e.length()));
+    s
+}
+
+fn scalars(s: &mut String, name: &str, values: impl Iterator<Item = f32>) {
+    let _ = writeln!(s, "SCALARS {name} float 1\nLOOKUP_TABLE default");
+    for v in values {
+        let _ = writeln!(s, "{v}");
+    }
+}
+
+fn vectors(s: &mut String, name: &str, values: &[Vec3]) {
+    let _ = writeln!(s, "VECTORS {name} float");
+    for v in values {
+        let _ = writeln!(s, "{} {} {}", v.x, v.y, v.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::LineKind;
+    use crate::trace::LinePoint;
+
+    fn two_lines() -> Vec<FieldLine> {
+        let pt = |x: f32| LinePoint {
+            pos: Vec3::new(x, 0.0, 0.0),
+            tangent: Vec3::X,
+            mag: 2.0,
+        };
+        vec![
+            FieldLine {
+                kind: LineKind::E,
+                points: vec![pt(0.0), pt(1.0), pt(2.0)],
+            },
+            FieldLine {
+                kind: LineKind::B,
+                points: vec![pt(5.0), pt(6.0)],
+            },
+        ]
+    }
+
+    #[test]
+    fn obj_and_vtk_index_points_consistently() {
+        let lines = two_lines();
+        let obj = lines_to_obj(&lines);
+        assert!(
+            obj.contains("l 1 2 3\n") && obj.contains("l 4 5\n"),
+            "{obj}"
+        );
+
+        let vtk = lines_to_vtk(&lines, |p| p.x, "t");
+        assert!(vtk.contains("POINTS 5 float\n"));
+        assert!(vtk.contains("LINES 2 7\n3 0 1 2\n2 3 4\n"), "{vtk}");
+        assert!(vtk.contains("CELL_DATA 2\nSCALARS kind int 1\nLOOKUP_TABLE default\n0\n1\n"));
+        assert!(vtk.contains("POINT_DATA 5\n"));
+        // arc length restarts at each line's seed
+        assert!(
+            vtk.ends_with("arc_length float 1\nLOOKUP_TABLE default\n0\n1\n2\n0\n1\n"),
+            "{vtk}"
+        );
+    }
+
+    #[test]
+    fn grid_vtk_lists_every_node() {
+        let charges = [crate::em3d::Charge3D {
+            pos: Vec3::ZERO,
+            q: 1.0,
+            vel: Vec3::ZERO,
+        }];
+        let grid = FieldSamples::sample(
+            &charges,
+            (Vec3::splat(-1.0), Vec3::ONE),
+            [3, 4, 5],
+            1.0,
+            0.01,
+            1.0,
+        );
+        assert_eq!(grid.spacing, Vec3::new(1.0, 2.0 / 3.0, 0.5));
+        assert!(grid.b.is_none());
+        let vtk = grid_to_vtk(&grid, "t");
+        assert!(vtk.contains("DIMENSIONS 3 4 5\nORIGIN -1 -1 -1\n"));
+        assert!(vtk.contains("POINT_DATA 60\nVECTORS E float\n"));
+        assert_eq!(vtk.lines().count(), 8 + 61 + 2 * 62);
+    }
+
+    #[test]
+    fn csv_has_a_row_per_point() {
+        let csv = lines_to_csv(&two_lines());
+        assert_eq!(csv.lines().count(), 1 + 5);
+        assert!(csv.contains("\n1,B,1,6,0,0,2\n"));
+    }
+}
@@ -0,0 +1,243 @@
+//! Translate gizmo: three axis arrows and three plane handles around an
+//! origin, sized to a fixed length on screen. Hit-testing, the constrained
+//! point under a ray, and grid snapping; drawing is left to the caller.
+//! Also the velocity arrows on selected charges, whose tips drag `vel`.
+
+use glam::{Mat4, Vec3};
+
+/// Handles span this fraction of the arm length, from the origin out.
+pub const PLANE_HANDLE: (f32, f32) = (0.25, 0.5);
+
+/// Pick tolerance around an arrow, as a fraction of the arm length.
+const ARROW_TOLERANCE: f32 = 0.08;
+
+pub const AXES: [Vec3; 3] = [Vec3::X, Vec3::Y, Vec3::Z];
+
+/// Velocity arrow length in world units per unit of speed.
+pub const VEL_SCALE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Handle {
+    /// Move along axis 0, 1 or 2.
+    Axis(usize),
+    /// Move in the plane normal to axis 0, 1 or 2.
+    Plane(usize),
+}
+
+impl Handle {
+    /// Whether moves through this handle change component `k`.
+    pub fn moves(self, k: usize) -> bool {
+        match self {
+            Self::Axis(a) => a == k,
+            Self::Plane(n) => n != k,
+        }
+    }
+
+    /// The point on this handle's line or plane through `origin` under the
+    /// ray, if the ray isn't (nearly) parallel to it.
+    pub fn point(self, origin: Vec3, ro: Vec3, rd: Vec3) -> Option<Vec3> {
+        match self {
+            Self::Axis(a) => {
+                closest_on_line(origin, AXES[a], ro, rd).map(|(s, _)| origin + AXES[a] * s)
+            }
+            Self::Plane(n) => {
+                let denom = rd.dot(AXES[n]);
+                if denom.abs() < 1e-4 {
+                    return None;
+                }
+                let t = (origin - ro).dot(AXES[n]) / denom;
+                (t > 0.0).then(|| ro + rd * t)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gizmo {
+    pub origin: Vec3,
+    /// Arm length in world units.
+    pub size: f32,
+}
+
+impl Gizmo {
+    /// A gizmo at `origin` whose arms are `arm_px` long on a viewport
+    /// `height_px` tall.
+    pub fn new(origin: Vec3, view_proj: Mat4, height_px: f32, arm_px: f32) -> Self {
+        Self {
+            origin,
+            size: arm_px * world_per_px(view_proj, origin, height_px),
+        }
+    }
+
+    /// The handle under the ray; plane handles win over the arrows through them.
+    /// Arrows start where the plane handles do, which leaves the origin itself
+    /// (the charge under the gizmo) free for other picks.
+    pub fn pick(&self, ro: Vec3, rd: Vec3) -> Option<Handle> {
+        let (lo, hi) = PLANE_HANDLE;
+        let plane = (0..3).find(|&n| {
+            let Some(p) = Handle::Plane(n).point(self.origin, ro, rd) else {
+                return false;
+            };
+            let d = (p - self.origin) / self.size;
+            (0..3)
+                .filter(|&k| k != n)
+                .all(|k| (lo..=hi).contains(&d[k]))
+        });
+        if let Some(n) = plane {
+            return Some(Handle::Plane(n));
+        }
+
+        (0..3)
+            .filter_map(|a| {
+                let (s, _) = closest_on_line(self.origin, AXES[a], ro, rd)?;
+                let on_axis = self.origin + AXES[a] * s.clamp(lo * self.size, self.size);
+                let t = (on_axis - ro).dot(rd);
+                let miss = on_axis.distance(ro + rd * t);
+                (t > 0.0 && miss < ARROW_TOLERANCE * self.size).then_some((a, miss))
+            })
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .map(|(a, _)| Handle::Axis(a))
+    }
+}
+
+/// Snap the components of `p` that `handle` moves (all of them for `None`,
+/// a free drag) to multiples of `step`.
+pub fn snap(p: Vec3, step: f32, handle: Option<Handle>) -> Vec3 {
+    if step <= 0.0 {
+        return p;
+    }
+    let mut out = p;
+    for k in 0..3 {
+        if handle.is_none_or(|h| h.moves(k)) {
+            out[k] = (p[k] / step).round() * step;
+        }
+    }
+    out
+}
+
+/// World length of one pixel at `p`, on a viewport `height_px` tall.
+pub fn world_per_px(view_proj: Mat4, p: Vec3, height_px: f32) -> f32 {
+    above(view_proj, p, 1.0, height_px).map_or(0.0, |up| up.distance(p))
+}
+
+/// Where the velocity arrow of a charge at `pos` ends. A still charge gets a
+/// stub `stub_px` long pointing up the screen, so there is always a tip to
+/// drag a velocity out of.
+pub fn velocity_tip(pos: Vec3, vel: Vec3, view_proj: Mat4, height_px: f32, stub_px: f32) -> Vec3 {
+    if vel.length_squared() > 1e-10 {
+        pos + vel * VEL_SCALE
+    } else {
+        above(view_proj, pos, stub_px, height_px).unwrap_or(pos)
+    }
+}
+
+/// `v` along its dominant axis only, keeping that component.
+pub fn snap_to_axis(v: Vec3) -> Vec3 {
+    let k = v.abs().max_position();
+    let mut out = Vec3::ZERO;
+    out[k] = v[k];
+    out
+}
+
+/// The point `px` pixels above `p` on screen at the same depth; `None` behind
+/// the camera.
+fn above(view_proj: Mat4, p: Vec3, px: f32, height_px: f32) -> Option<Vec3> {
+    let clip = view_proj * p.extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.truncate() / clip.w;
+    let up = Vec3::new(0.0, 2.0 * px / height_px.max(1.0), 0.0);
+    Some(view_proj.inverse().project_point3(ndc + up))
+}
+
+/// Parameters `(s, t)` of the closest points on the line `o + a s` (unit `a`)
+/// and the ray `ro + rd t`; `None` when they are parallel.
+fn closest_on_line(o: Vec3, a: Vec3, ro: Vec3, rd: Vec3) -> Option<(f32, f32)> {
+    let w0 = o - ro;
+    let b = a.dot(rd);
+    let denom = 1.0 - b * b;
+    if denom < 1e-6 {
+        return None;
+    }
+    let (d, e) = (a.dot(w0), rd.dot(w0));
+    Some(((b * e - d) / denom, (e - b * d) / denom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Orbit};
+
+    #[test]
+    fn picks_handles_and_constrains_moves() {
+        let mut cam = Camera::new(1.0);
+        cam.update_from_orbit(&Orbit {
+            yaw: 0.6,
+            pitch: 0.4,
+            radius: 6.0,
+            target: Vec3::ZERO,
+        });
+        let vp = cam.proj() * cam.view();
+        let gizmo = Gizmo::new(Vec3::ZERO, vp, 600.0, 80.0);
+        assert!(gizmo.size > 0.0);
+        // 80 px at this distance: 80 / 600 of the view height
+        let view_h = 2.0 * 6.0 * (cam.fov_y / 2.0).tan();
+        assert!((gizmo.size - view_h * 80.0 / 600.0).abs() < 1e-3 * view_h);
+
+        let ray_at = |p: Vec3| (cam.eye, (p - cam.eye).normalize());
+        let (ro, rd) = ray_at(Vec3::X * gizmo.size * 0.8);
+        assert_eq!(gizmo.pick(ro, rd), Some(Handle::Axis(0)));
+        let (ro, rd) = ray_at(Vec3::new(0.4, 0.0, 0.4) * gizmo.size);
+        assert_eq!(gizmo.pick(ro, rd), Some(Handle::Plane(1)));
+        let (ro, rd) = ray_at(Vec3::ONE * 3.0 * gizmo.size);
+        assert_eq!(gizmo.pick(ro, rd), None);
+        let (ro, rd) = ray_at(Vec3::ZERO);
+        assert_eq!(gizmo.pick(ro, rd), None);
+
+        // the axis-constrained point under any ray stays on the axis
+        let (ro, rd) = ray_at(Vec3::new(1.0, 0.3, -0.2));
+        let p = Handle::Axis(0).point(Vec3::ZERO, ro, rd).unwrap();
+        assert!(p.y.abs() < 1e-5 && p.z.abs() < 1e-5);
+        assert!((p.x - 1.0).abs() < 0.3);
+        let p = Handle::Plane(1).point(Vec3::ZERO, ro, rd).unwrap();
+        assert!(p.y.abs() < 1e-5);
+
+        let p = Vec3::new(0.26, 0.74, -0.13);
+        assert_eq!(
+            snap(p, 0.25, Some(Handle::Axis(1))),
+            Vec3::new(0.26, 0.75, -0.13)
+        );
+        assert_eq!(
+            snap(p, 0.25, Some(Handle::Plane(1))),
+            Vec3::new(0.25, 0.74, -0.25)
+        );
+        assert_eq!(snap(p, 0.5, None), Vec3::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn velocity_tips_and_axis_snap() {
+        let mut cam = Camera::new(1.0);
+        cam.update_from_orbit(&Orbit {
+            yaw: 0.0,
+            pitch: 0.0,
+            radius: 5.0,
+            target: Vec3::ZERO,
+        });
+        let vp = cam.proj() * cam.view();
+        let pos = Vec3::new(0.5, 0.0, 0.0);
+        let v = Vec3::new(0.0, 2.0, 0.0);
+        assert_eq!(velocity_tip(pos, v, vp, 600.0, 40.0), pos + v * VEL_SCALE);
+
+        // a still charge's stub points up the screen, 40 px long
+        let stub = velocity_tip(pos, Vec3::ZERO, vp, 600.0, 40.0) - pos;
+        let px = world_per_px(vp, pos, 600.0);
+        assert!((stub.length() - 40.0 * px).abs() < 1e-3, "{stub:?}");
+        assert!(stub.normalize().dot(cam.up) > 0.99, "{stub:?}");
+
+        assert_eq!(
+            snap_to_axis(Vec3::new(0.3, -0.9, 0.5)),
+            Vec3::new(0.0, -0.9, 0.0)
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Binary glTF (.glb) export: field lines as coloured tubes and charges as
+//! spheres. Ribbons are screen-space in the renderers, so the export builds
+//! real geometry that reads well from any angle in Blender or a web viewer.
+
+use crate::colormap::Colormaps;
+use crate::em3d::Charge3D;
+use crate::scene::{FieldLine, LineKind};
+use crate::trace::tone_from_mag;
+use glam::Vec3;
+use std::f32::consts::{PI, TAU};
+use std::fmt::Write as _;
+
+/// Indexed triangle mesh with per-vertex colours.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub colors: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    fn named(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    fn push(&mut self, p: Vec3, n: Vec3, color: [f32; 3]) {
+        self.positions.push(p.to_array());
+        self.normals.push(n.to_array());
+        self.colors.push(color);
+    }
+}
+
+/// Sweep a `sides`-gon of `radius` along every line, coloured by the palette
+/// of its kind. Frames are parallel-transported so tubes don't twist.
+pub fn tube_mesh(
+    name: &str,
+    lines: &[FieldLine],
+    colormaps: Colormaps,
+    radius: f32,
+    sides: u32,
+) -> Mesh {
+    let sides = sides.max(3);
+    let mut mesh = Mesh::named(name);
+    for line in lines.iter().filter(|l| l.points.len() >= 2) {
+        let palette = colormaps.of(line.kind);
+        let first = mesh.positions.len() as u32;
+        let mut t = Vec3::ZERO;
+        let mut n = Vec3::ZERO;
+        for (i, pt) in line.points.iter().enumerate() {
+            // the tracer's tangent is zero where the field vanishes; keep the last one
+            let next_t = match pt.tangent.try_normalize() {
+                Some(v) => v,
+                None if i == 0 => (line.points[1].pos - pt.pos).normalize_or(Vec3::Z),
+                None => t,
+            };
+            n = if i == 0 {
+                next_t.any_orthonormal_vector()
+            } else {
+                (n - next_t * n.dot(next_t))
+                    .try_normalize()
+                    .unwrap_or_else(|| next_t.any_orthonormal_vector())
+            };
+            t = next_t;
+            let b = t.cross(n);
+            let color = palette.color(tone_from_mag(pt.mag));
+            for s in 0..sides {
+                let a = TAU * s as f32 / sides as f32;
+                let dir = n * a.cos() + b * a.sin();
+                mesh.push(pt.pos + radius * dir, dir, color);
+            }
+        }
+        for i in 0..line.points.len() as u32 - 1 {
+            let ring = first + i * sides;
+            for s in 0..sides {
+                let (a, b) = (ring + s, ring + (s + 1) % sides);
+                let (c, d) = (a + sides, b + sides);
+                mesh.indices.extend([a, b, c, b, d, c]);
+            }
+        }
+    }
+    mesh
+}
+
+/// One UV sphere of `radius` per charge, in the renderers' impostor grey.
+pub fn sphere_mesh(name: &str, charges: &[Charge3D], radius: f32) -> Mesh {
+    const STACKS: u32 = 12;
+    const SLICES: u32 = 16;
+    let mut mesh = Mesh::named(name);
+    for c in charges {
+        let first = mesh.positions.len() as u32;
+        for i in 0..=STACKS {
+            let phi = PI * i as f32 / STACKS as f32;
+            for j in 0..=SLICES {
+                let theta = TAU * j as f32 / SLICES as f32;
+                let n = Vec3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin());
+                mesh.push(c.pos + radius * n, n, [0.95; 3]);
+            }
+        }
+        for i in 0..STACKS {
+            for j in 0..SLICES {
+                let a = first + i * (SLICES + 1) + j;
+                let b = a + SLICES + 1;
+                mesh.indices.extend([a, a + 1, b, a + 1, b + 1, b]);
+            }
+        }
+    }
+    mesh
+}
+
+/// Tube radius and sphere radius in world units, sized for the default scene.
+pub const TUBE_RADIUS: f32 = 0.004;
+pub const SPHERE_RADIUS: f32 = 0.04;
+
+/// Charges and traced lines as a ready-to-save .glb, one mesh per line kind.
+pub fn scene_glb(charges: &[Charge3D], lines: &[FieldLine], colormaps: Colormaps) -> Vec<u8> {
+    let mut meshes = vec![sphere_mesh("charges", charges, SPHERE_RADIUS)];
+    for kind in [LineKind::E, LineKind::B] {
+        let of_kind: Vec<FieldLine> = lines.iter().filter(|l| l.kind == kind).cloned().collect();
+        let name = format!("{}_lines", kind.name());
+        meshes.push(tube_mesh(&name, &of_kind, colormaps, TUBE_RADIUS, 6));
+    }
+    write_glb(&meshes)
+}
+
+const FLOAT: u32 = 5126;
+const UINT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Binary chunk plus the bufferView / accessor JSON that points into it.
+#[derive(Default)]
+struct GlbBuffers {
+    bin: Vec<u8>,
+    views: Vec<String>,
+    accessors: Vec<String>,
+}
+
+impl GlbBuffers {
+    fn accessor(&mut self, bytes: &[u8], target: u32, desc: String) -> usize {
+        let (offset, len) = (self.bin.len(), bytes.len());
+        self.bin.extend_from_slice(bytes);
+        self.views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{offset},"byteLength":{len},"target":{target}}}"#
+        ));
+        let view = self.views.len() - 1;
+        self.accessors
+            .push(format!(r#"{{"bufferView":{view},{desc}}}"#));
+        self.accessors.len() - 1
+    }
+
+    fn vec3(&mut self, data: &[[f32; 3]], extra: &str) -> usize {
+        let bytes: Vec<u8> = data
+            .iter()
+            .flatten()
+            .flat_map(|f| f.to_le_bytes())
+            .collect();
+        let desc = format!(
+            r#""componentType":{FLOAT},"count":{},"type":"VEC3"{extra}"#,
+            data.len()
+        );
+        self.accessor(&bytes, ARRAY_BUFFER, desc)
+    }
+
+    fn indices(&mut self, data: &[u32]) -> usize {
+        let bytes: Vec<u8> = data.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let desc = format!(
+            r#""componentType":{UINT},"count":{},"type":"SCALAR""#,
+            data.len()
+        );
+        self.accessor(&bytes, ELEMENT_ARRAY_BUFFER, desc)
+    }
+}
+
+/// Pack `meshes` into one .glb: a node per non-empty mesh, all sharing a plain
+/// white material so the vertex colours show through.
+pub fn write_glb(meshes: &[Mesh]) -> Vec<u8> {
+    let mut buf = GlbBuffers::default();
+    let mut json_meshes = Vec::new();
+    for mesh in meshes.iter().filter(|m| !m.indices.is_empty()) {
+        // POSITION must carry min/max
+        let (lo, hi) = mesh.positions.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(lo, hi), p| (lo.min(Vec3::from(*p)), hi.max(Vec3::from(*p))),
+        );
+        let bounds = format!(
+            r#","min":[{},{},{}],"max":[{},{},{}]"#,
+            lo.x, lo.y, lo.z, hi.x, hi.y, hi.z
+        );
+        let pos = buf.vec3(&mesh.positions, &bounds);
+        let nrm = buf.vec3(&mesh.normals, "");
+        let col = buf.vec3(&mesh.colors, "");
+        let idx = buf.indices(&mesh.indices);
+        json_meshes.push(format!(
+            concat!(
+                r#"{{"name":{},"primitives":[{{"attributes":"#,
+                r#"{{"POSITION":{},"NORMAL":{},"COLOR_0":{}}},"indices":{},"material":0,"mode":4}}]}}"#
+            ),
+            json_string(&mesh.name),
+            pos,
+            nrm,
+            col,
+            idx
+        ));
+    }
+
+    let nodes: Vec<String> = (0..json_meshes.len())
+        .map(|i| format!(r#"{{"mesh":{i}}}"#))
+        .collect();
+    let node_ids: Vec<String> = (0..nodes.len()).map(|i| i.to_string()).collect();
+    let mut json = String::new();
+    let _ = write!(
+        json,
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"em_viz"}},"scene":0,"#,
+            r#""scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}],"#,
+            r#""materials":[{{"pbrMetallicRoughness":{{"baseColorFactor":[1,1,1,1],"metallicFactor":0,"roughnessFactor":0.6}},"doubleSided":true}}],"#,
+            r#""accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#
+        ),
+        node_ids.join(","),
+        nodes.join(","),
+        json_meshes.join(","),
+        buf.accessors.join(","),
+        buf.views.join(","),
+        buf.bin.len()
+    );
+    let mut bin = buf.bin;
+
+    // chunks are 4-byte aligned: JSON pads with spaces, BIN with zeros
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+
+    let total = 12 + 8 + json.len() + 8 + bin.len();
+    let mut out = Vec::with_capacity(total);
+    out.extend_from_slice(b"glTF");
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&(total as u32).to_le_bytes());
+    for (kind, chunk) in [(b"JSON", &json), (b"BIN\0", &bin)] {
+        out.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        out.extend_from_slice(kind);
+        out.extend_from_slice(chunk);
+    }
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::LinePoint;
+
+    #[test]
+    fn glb_header_and_chunks_are_consistent() {
+        let line = FieldLine {
+            kind: LineKind::E,
+            points: (0..4)
+                .map(|i| LinePoint {
+                    pos: Vec3::new(i as f32, 0.0, 0.0),
+                    tangent: Vec3::X,
+                    mag: 1.0,
+                })
+                .collect(),
+        };
+        let tubes = tube_mesh("E", &[line], Colormaps::default(), 0.1, 6);
+        assert_eq!(tubes.positions.len(), 4 * 6);
+        assert_eq!(tubes.indices.len(), 3 * 6 * 6);
+        assert!(
+            tubes
+                .indices
+                .iter()
+                .all(|&i| (i as usize) < tubes.positions.len())
+        );
+
+        let glb = write_glb(&[tubes, Mesh::named("empty")]);
+        let word = |at: usize| u32::from_le_bytes(glb[at..at + 4].try_into().unwrap()) as usize;
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(word(8), glb.len());
+        let json_len = word(12);
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(json_len % 4, 0);
+        let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+        assert!(json.contains(r#""meshes":[{"name":"E""#), "{json}");
+        let bin_len = word(20 + json_len);
+        assert_eq!(&glb[24 + json_len..28 + json_len], b"BIN\0");
+        assert_eq!(28 + json_len + bin_len, glb.len());
+        assert!(json.contains(&format!(r#""buffers":[{{"byteLength":{bin_len}}}]"#)));
+    }
+}
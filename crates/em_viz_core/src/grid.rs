@@ -0,0 +1,81 @@
+//! Regular sample grids over a box around the charges: the bounds used by the
+//! GPU field bake and CPU samples of E, V and B for export.
+
+use crate::em3d::{Charge3D, sample_b3d, sample_e3d, sample_v3d};
+use glam::Vec3;
+
+/// Cubic box around the charges, padded so lines leaving a charge cluster stay on
+/// the grid for a while before falling back to the exact field.
+pub fn scene_bounds(charges: &[Charge3D]) -> (Vec3, Vec3) {
+    if charges.is_empty() {
+        return (Vec3::splat(-1.0), Vec3::splat(1.0));
+    }
+    let (lo, hi) = charges.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(lo, hi), c| (lo.min(c.pos), hi.max(c.pos)),
+    );
+    let center = 0.5 * (lo + hi);
+    let half = 0.5 * (hi - lo).max_element();
+    let half = half + (0.5 * half).max(1.0);
+    (center - Vec3::splat(half), center + Vec3::splat(half))
+}
+
+/// Field values on the nodes of a regular grid, x varying fastest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldSamples {
+    pub dims: [usize; 3],
+    pub origin: Vec3,
+    pub spacing: Vec3,
+    pub e: Vec<Vec3>,
+    pub v: Vec<f32>,
+    /// Only sampled when some charge moves; B is zero otherwise.
+    pub b: Option<Vec<Vec3>>,
+}
+
+impl FieldSamples {
+    /// Sample `dims` nodes spanning `lo..=hi` (each axis needs at least 2).
+    pub fn sample(
+        charges: &[Charge3D],
+        (lo, hi): (Vec3, Vec3),
+        dims: [usize; 3],
+        k: f32,
+        soft2: f32,
+        c_inv2: f32,
+    ) -> Self {
+        let dims = dims.map(|d| d.max(2));
+        let cells = Vec3::new(dims[0] as f32, dims[1] as f32, dims[2] as f32) - Vec3::ONE;
+        let spacing = (hi - lo) / cells;
+        let points: Vec<Vec3> = (0..dims[2])
+            .flat_map(|z| (0..dims[1]).flat_map(move |y| (0..dims[0]).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| lo + spacing * Vec3::new(x as f32, y as f32, z as f32))
+            .collect();
+        let moving = charges.iter().any(|c| c.vel.length_squared() >= 1e-10);
+        Self {
+            dims,
+            origin: lo,
+            spacing,
+            e: points
+                .iter()
+                .map(|&p| sample_e3d(p, charges, k, soft2))
+                .collect(),
+            v: points
+                .iter()
+                .map(|&p| sample_v3d(p, charges, k, soft2))
+                .collect(),
+            b: moving.then(|| {
+                points
+                    .iter()
+                    .map(|&p| sample_b3d(p, charges, k, soft2, c_inv2))
+                    .collect()
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.dims.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
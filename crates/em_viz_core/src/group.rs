@@ -0,0 +1,152 @@
+//! Operations on a selected group of charges: box selection and transforms
+//! about the group's centroid. Transforms return the changes rather than
+//! applying them, so callers can record them as one undo step.
+
+use crate::em3d::Charge3D;
+use crate::history::ChargeChange;
+use glam::{Mat4, Quat, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupOp {
+    /// Positions and velocities about the centroid, `angle` in radians.
+    Rotate { axis: Vec3, angle: f32 },
+    /// Distances from the centroid.
+    Scale(f32),
+    /// Reflect through the plane through the centroid with this normal.
+    Mirror(Vec3),
+    /// Flip every charge's sign.
+    Negate,
+}
+
+/// Mean position of the charges at `idx`.
+pub fn centroid(charges: &[Charge3D], idx: &[usize]) -> Option<Vec3> {
+    let ps: Vec<Vec3> = idx
+        .iter()
+        .filter_map(|&i| charges.get(i).map(|c| c.pos))
+        .collect();
+    (!ps.is_empty()).then(|| ps.iter().sum::<Vec3>() / ps.len() as f32)
+}
+
+impl GroupOp {
+    /// What the op does to the charges at `idx`, one change per charge.
+    pub fn changes(self, charges: &[Charge3D], idx: &[usize]) -> Vec<ChargeChange> {
+        let Some(c) = centroid(charges, idx) else {
+            return vec![];
+        };
+        idx.iter()
+            .filter_map(|&i| {
+                let before = *charges.get(i)?;
+                let mut after = before;
+                match self {
+                    Self::Rotate { axis, angle } => {
+                        let r = Quat::from_axis_angle(axis.normalize_or(Vec3::Y), angle);
+                        after.pos = c + r * (before.pos - c);
+                        after.vel = r * before.vel;
+                    }
+                    Self::Scale(s) => after.pos = c + s * (before.pos - c),
+                    Self::Mirror(n) => {
+                        let n = n.normalize_or(Vec3::X);
+                        let reflect = |v: Vec3| v - 2.0 * v.dot(n) * n;
+                        after.pos = c + reflect(before.pos - c);
+                        after.vel = reflect(before.vel);
+                    }
+                    Self::Negate => after.q = -before.q,
+                }
+                Some(ChargeChange {
+                    idx: i,
+                    before,
+                    after,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Charges whose centres project inside the NDC rectangle spanned by `a` and
+/// `b`, in front of the camera.
+pub fn in_ndc_box(
+    charges: &[Charge3D],
+    view_proj: Mat4,
+    a: (f32, f32),
+    b: (f32, f32),
+) -> Vec<usize> {
+    let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+    let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+    charges
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            let clip = view_proj * c.pos.extend(1.0);
+            let (x, y) = (clip.x / clip.w, clip.y / clip.w);
+            clip.w > 0.0 && (x0..=x1).contains(&x) && (y0..=y1).contains(&y)
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Orbit};
+    use std::f32::consts::FRAC_PI_2;
+
+    fn at(x: f32, y: f32, q: f32) -> Charge3D {
+        Charge3D {
+            pos: Vec3::new(x, y, 0.0),
+            q,
+            vel: Vec3::X,
+        }
+    }
+
+    fn apply(charges: &mut [Charge3D], op: GroupOp, idx: &[usize]) {
+        for c in op.changes(charges, idx) {
+            charges[c.idx] = c.after;
+        }
+    }
+
+    #[test]
+    fn transforms_keep_the_centroid() {
+        let mut cs = vec![at(1.0, 0.0, 1.0), at(3.0, 0.0, -1.0), at(9.0, 9.0, 1.0)];
+        let group = [0, 1];
+        let c = centroid(&cs, &group).unwrap();
+        assert_eq!(c, Vec3::new(2.0, 0.0, 0.0));
+
+        let rot = GroupOp::Rotate {
+            axis: Vec3::Z,
+            angle: FRAC_PI_2,
+        };
+        apply(&mut cs, rot, &group);
+        assert!(cs[0].pos.distance(Vec3::new(2.0, -1.0, 0.0)) < 1e-5);
+        assert!(cs[0].vel.distance(Vec3::Y) < 1e-5);
+        assert_eq!(cs[2], at(9.0, 9.0, 1.0));
+
+        apply(&mut cs, GroupOp::Scale(2.0), &group);
+        assert!(cs[1].pos.distance(Vec3::new(2.0, 2.0, 0.0)) < 1e-5);
+
+        apply(&mut cs, GroupOp::Mirror(Vec3::Y), &group);
+        assert!(cs[1].pos.distance(Vec3::new(2.0, -2.0, 0.0)) < 1e-5);
+        assert!(centroid(&cs, &group).unwrap().distance(c) < 1e-5);
+
+        apply(&mut cs, GroupOp::Negate, &group);
+        assert_eq!((cs[0].q, cs[1].q, cs[2].q), (-1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn box_selects_what_projects_inside() {
+        let mut cam = Camera::new(1.0);
+        cam.update_from_orbit(&Orbit {
+            yaw: 0.0,
+            pitch: 0.0,
+            radius: 5.0,
+            target: Vec3::ZERO,
+        });
+        let vp = cam.proj() * cam.view();
+        let behind = Charge3D {
+            pos: cam.eye * 2.0,
+            ..at(0.0, 0.0, 1.0)
+        };
+        let cs = vec![at(0.0, 0.0, 1.0), behind];
+        assert_eq!(in_ndc_box(&cs, vp, (-0.5, -0.5), (0.5, 0.5)), vec![0]);
+        assert!(in_ndc_box(&cs, vp, (0.5, 0.5), (0.9, 0.9)).is_empty());
+    }
+}
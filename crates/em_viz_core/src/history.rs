@@ -0,0 +1,220 @@
+//! Undo / redo. [`History`] only orders edits and folds consecutive ones into
+//! one step; what an edit is and how it applies stays with the caller.
+//! [`ChargeEdit`] covers changes to a charge list.
+
+use crate::em3d::Charge3D;
+
+pub trait Coalesce: Sized {
+    /// Fold `next` into `self` when the two make one undo step (one drag, one
+    /// slider gesture), or hand it back.
+    fn coalesce(&mut self, next: Self) -> Result<(), Self>;
+}
+
+#[derive(Clone, Debug)]
+pub struct History<E> {
+    done: Vec<E>,
+    undone: Vec<E>,
+    limit: usize,
+}
+
+impl<E: Coalesce> History<E> {
+    /// Keeps at most `limit` undo steps.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            done: Vec::new(),
+            undone: Vec::new(),
+            limit: limit.max(1),
+        }
+    }
+
+    /// Record an edit that has just been applied. Clears the redo stack.
+    pub fn record(&mut self, edit: E) {
+        self.undone.clear();
+        let edit = match self.done.last_mut() {
+            Some(top) => match top.coalesce(edit) {
+                Ok(()) => return,
+                Err(edit) => edit,
+            },
+            None => edit,
+        };
+        self.done.push(edit);
+        if self.done.len() > self.limit {
+            self.done.remove(0);
+        }
+    }
+
+    /// The edit to revert, moved onto the redo stack.
+    pub fn undo(&mut self) -> Option<&E> {
+        let edit = self.done.pop()?;
+        self.undone.push(edit);
+        self.undone.last()
+    }
+
+    /// The edit to apply again, moved back onto the undo stack.
+    pub fn redo(&mut self) -> Option<&E> {
+        let edit = self.undone.pop()?;
+        self.done.push(edit);
+        self.done.last()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+}
+
+/// One charge's value before and after an update.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChargeChange {
+    pub idx: usize,
+    pub before: Charge3D,
+    pub after: Charge3D,
+}
+
+/// A reversible change to a charge list.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChargeEdit {
+    Insert {
+        idx: usize,
+        charge: Charge3D,
+    },
+    Remove {
+        idx: usize,
+        charge: Charge3D,
+    },
+    /// Position, charge or velocity of some charges (moves, value edits).
+    Update(Vec<ChargeChange>),
+    /// The whole list (presets, loaded files).
+    Replace {
+        before: Vec<Charge3D>,
+        after: Vec<Charge3D>,
+    },
+}
+
+impl ChargeEdit {
+    pub fn apply(&self, charges: &mut Vec<Charge3D>) {
+        match self {
+            Self::Insert { idx, charge } => charges.insert((*idx).min(charges.len()), *charge),
+            Self::Remove { idx, .. } => {
+                if *idx < charges.len() {
+                    charges.remove(*idx);
+                }
+            }
+            Self::Update(changes) => {
+                for c in changes {
+                    if let Some(slot) = charges.get_mut(c.idx) {
+                        *slot = c.after;
+                    }
+                }
+            }
+            Self::Replace { after, .. } => after.clone_into(charges),
+        }
+    }
+
+    pub fn revert(&self, charges: &mut Vec<Charge3D>) {
+        match self {
+            Self::Insert { idx, .. } => {
+                if *idx < charges.len() {
+                    charges.remove(*idx);
+                }
+            }
+            Self::Remove { idx, charge } => charges.insert((*idx).min(charges.len()), *charge),
+            Self::Update(changes) => {
+                for c in changes.iter().rev() {
+                    if let Some(slot) = charges.get_mut(c.idx) {
+                        *slot = c.before;
+                    }
+                }
+            }
+            Self::Replace { before, .. } => before.clone_into(charges),
+        }
+    }
+}
+
+impl Coalesce for ChargeEdit {
+    /// Updates of the same charges merge, keeping the first `before`.
+    fn coalesce(&mut self, next: Self) -> Result<(), Self> {
+        match (self, next) {
+            (Self::Update(mine), Self::Update(theirs))
+                if mine.len() == theirs.len()
+                    && mine.iter().zip(&theirs).all(|(a, b)| a.idx == b.idx) =>
+            {
+                for (a, b) in mine.iter_mut().zip(theirs) {
+                    a.after = b.after;
+                }
+                Ok(())
+            }
+            (_, next) => Err(next),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    fn charge(x: f32) -> Charge3D {
+        Charge3D {
+            pos: Vec3::new(x, 0.0, 0.0),
+            q: 1.0,
+            vel: Vec3::ZERO,
+        }
+    }
+
+    fn record(history: &mut History<ChargeEdit>, charges: &mut Vec<Charge3D>, edit: ChargeEdit) {
+        edit.apply(charges);
+        history.record(edit);
+    }
+
+    #[test]
+    fn drag_steps_coalesce_and_undo_redo_round_trips() {
+        let start = vec![charge(0.0), charge(1.0)];
+        let mut charges = start.clone();
+        let mut history = History::new(10);
+
+        // a drag in three steps is one undo step
+        for x in [1.1, 1.2, 1.3] {
+            let change = ChargeChange {
+                idx: 1,
+                before: charges[1],
+                after: charge(x),
+            };
+            record(&mut history, &mut charges, ChargeEdit::Update(vec![change]));
+        }
+        record(
+            &mut history,
+            &mut charges,
+            ChargeEdit::Insert {
+                idx: 0,
+                charge: charge(-1.0),
+            },
+        );
+        assert_eq!(charges.len(), 3);
+
+        history.undo().unwrap().clone().revert(&mut charges);
+        assert_eq!(charges, vec![charge(0.0), charge(1.3)]);
+        history.undo().unwrap().clone().revert(&mut charges);
+        assert_eq!(charges, start);
+        assert!(!history.can_undo());
+
+        history.redo().unwrap().clone().apply(&mut charges);
+        assert_eq!(charges[1], charge(1.3));
+        // a new edit drops what could be redone
+        let first = charges[0];
+        record(
+            &mut history,
+            &mut charges,
+            ChargeEdit::Remove {
+                idx: 0,
+                charge: first,
+            },
+        );
+        assert!(!history.can_redo());
+        history.undo().unwrap().clone().revert(&mut charges);
+        assert_eq!(charges, vec![charge(0.0), charge(1.3)]);
+    }
+}
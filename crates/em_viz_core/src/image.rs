@@ -0,0 +1,111 @@
+//! 8-bit RGBA images read back from the GPU: row unpadding, supersample
+//! reduction and alpha handling before encoding.
+
+/// Tightly packed RGBA8 pixels, rows top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Copy out of a readback buffer whose rows are `row_bytes` apart (GPU copies
+    /// pad rows to 256 bytes). `bgra` swaps red and blue for BGRA surfaces.
+    pub fn from_padded_rows(
+        data: &[u8],
+        width: u32,
+        height: u32,
+        row_bytes: usize,
+        bgra: bool,
+    ) -> Self {
+        let w4 = width as usize * 4;
+        let mut pixels = Vec::with_capacity(w4 * height as usize);
+        for row in data.chunks(row_bytes).take(height as usize) {
+            pixels.extend_from_slice(&row[..w4]);
+        }
+        if bgra {
+            pixels
+                .as_chunks_mut::<4>()
+                .0
+                .iter_mut()
+                .for_each(|px| px.swap(0, 2));
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Box-filter down by `factor` in each axis (the size must be a multiple).
+    /// Expects premultiplied alpha, so transparent texels don't bleed colour.
+    pub fn downsample(&self, factor: u32) -> Self {
+        if factor <= 1 {
+            return self.clone();
+        }
+        let (w, h) = (self.width / factor, self.height / factor);
+        let n = factor * factor;
+        let mut pixels = vec![0u8; (w * h * 4) as usize];
+        for y in 0..h {
+            for x in 0..w {
+                let mut sum = [0u32; 4];
+                for sy in 0..factor {
+                    let row = ((y * factor + sy) * self.width + x * factor) as usize * 4;
+                    for px in self.pixels[row..row + factor as usize * 4]
+                        .as_chunks::<4>()
+                        .0
+                    {
+                        for c in 0..4 {
+                            sum[c] += px[c] as u32;
+                        }
+                    }
+                }
+                let out = ((y * w + x) * 4) as usize;
+                for c in 0..4 {
+                    pixels[out + c] = ((sum[c] + n / 2) / n) as u8;
+                }
+            }
+        }
+        Self {
+            width: w,
+            height: h,
+            pixels,
+        }
+    }
+
+    /// Premultiplied to straight alpha, as PNG expects.
+    pub fn unpremultiply(&mut self) {
+        for px in self.pixels.as_chunks_mut::<4>().0 {
+            let a = px[3] as u32;
+            if a != 0 && a != 255 {
+                for c in &mut px[..3] {
+                    *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_averages_blocks_and_unpremultiplies() {
+        // 4x2 with 256-byte padded rows; left 2x2 block half covered by red at
+        // full alpha, right block fully transparent
+        let mut data = vec![0u8; 2 * 256];
+        data[0..4].copy_from_slice(&[0, 0, 200, 255]); // BGRA red
+        data[256..260].copy_from_slice(&[0, 0, 200, 255]);
+        let img = RgbaImage::from_padded_rows(&data, 4, 2, 256, true);
+        assert_eq!(img.pixels.len(), 4 * 2 * 4);
+        assert_eq!(&img.pixels[..4], &[200, 0, 0, 255]);
+
+        let mut small = img.downsample(2);
+        assert_eq!((small.width, small.height), (2, 1));
+        assert_eq!(&small.pixels, &[100, 0, 0, 128, 0, 0, 0, 0]);
+        small.unpremultiply();
+        assert_eq!(&small.pixels[..4], &[199, 0, 0, 128]);
+    }
+}
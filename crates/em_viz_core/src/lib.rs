@@ -0,0 +1,25 @@
+//! Platform-independent physics, seeding and camera math for em_viz. No web or
+//! GPU dependencies, so it builds and tests natively and can be reused by tools.
+
+pub mod anim;
+pub mod camera;
+pub mod colormap;
+pub mod em3d;
+pub mod export;
+pub mod gizmo;
+pub mod gltf;
+pub mod grid;
+pub mod group;
+pub mod history;
+pub mod image;
+pub mod presets;
+pub mod ray;
+pub mod scene;
+#[cfg(feature = "serde")]
+pub mod scene_file;
+pub mod seed;
+#[cfg(feature = "share")]
+pub mod share;
+pub mod svg;
+pub mod trace;
+pub mod zip;
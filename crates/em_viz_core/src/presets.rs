@@ -0,0 +1,226 @@
+//! Classic charge configurations, generated from a few parameters. Layouts are
+//! centred on the origin; flat ones lie in the y–z plane, which faces the
+//! default camera.
+
+use crate::camera::Orbit;
+use crate::em3d::Charge3D;
+use crate::scene::Seeding;
+use glam::Vec3;
+use std::f32::consts::TAU;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Dipole,
+    LikePair,
+    LinearQuadrupole,
+    SquareQuadrupole,
+    Ring,
+    ParallelPlates,
+    FaradayRing,
+    ImageCharge,
+    MovingCharge,
+}
+
+/// What a preset is generated from. Not every preset uses every field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PresetParams {
+    /// Charges per ring, or per plate side.
+    pub n: usize,
+    /// Distance between charges, ring radius or plate gap.
+    pub spacing: f32,
+    /// Charge magnitude; spread over the charges of rings and plates.
+    pub q: f32,
+}
+
+/// A generated configuration: charges plus seeding and a camera that fit it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PresetScene {
+    pub charges: Vec<Charge3D>,
+    pub seeding: Seeding,
+    pub camera: Orbit,
+}
+
+impl Preset {
+    pub const ALL: [Self; 9] = [
+        Self::Dipole,
+        Self::LikePair,
+        Self::LinearQuadrupole,
+        Self::SquareQuadrupole,
+        Self::Ring,
+        Self::ParallelPlates,
+        Self::FaradayRing,
+        Self::ImageCharge,
+        Self::MovingCharge,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dipole => "Dipole",
+            Self::LikePair => "Like-charge pair",
+            Self::LinearQuadrupole => "Linear quadrupole",
+            Self::SquareQuadrupole => "Square quadrupole",
+            Self::Ring => "Charged ring",
+            Self::ParallelPlates => "Parallel-plate capacitor",
+            Self::FaradayRing => "Faraday-cage ring",
+            Self::ImageCharge => "Charge near grounded plane",
+            Self::MovingCharge => "Moving charge (B)",
+        }
+    }
+
+    /// Stable identifier for UIs and files.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Dipole => "dipole",
+            Self::LikePair => "like_pair",
+            Self::LinearQuadrupole => "linear_quadrupole",
+            Self::SquareQuadrupole => "square_quadrupole",
+            Self::Ring => "ring",
+            Self::ParallelPlates => "parallel_plates",
+            Self::FaradayRing => "faraday_ring",
+            Self::ImageCharge => "image_charge",
+            Self::MovingCharge => "moving_charge",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.id() == id)
+    }
+
+    /// Whether `n` changes the layout.
+    pub fn uses_n(self) -> bool {
+        matches!(self, Self::Ring | Self::ParallelPlates | Self::FaradayRing)
+    }
+
+    pub fn defaults(self) -> PresetParams {
+        let (n, spacing) = match self {
+            Self::Ring | Self::FaradayRing => (16, 0.6),
+            Self::ParallelPlates => (6, 0.6),
+            Self::ImageCharge => (1, 0.4),
+            _ => (1, 0.8),
+        };
+        PresetParams { n, spacing, q: 1.0 }
+    }
+
+    pub fn build(self, p: &PresetParams) -> PresetScene {
+        let (d, q) = (p.spacing.max(1e-3), p.q);
+        let n = p.n.max(1);
+        // y up, z across the screen
+        let at = |y: f32, z: f32, q: f32| Charge3D {
+            pos: Vec3::new(0.0, y, z),
+            q,
+            vel: Vec3::ZERO,
+        };
+        let ring = |r: f32, q_total: f32| {
+            (0..n).map(move |i| {
+                let a = TAU * i as f32 / n as f32;
+                at(r * a.sin(), r * a.cos(), q_total / n as f32)
+            })
+        };
+
+        let charges: Vec<Charge3D> = match self {
+            Self::Dipole => vec![at(0.0, d / 2.0, q), at(0.0, -d / 2.0, -q)],
+            Self::LikePair => vec![at(0.0, d / 2.0, q), at(0.0, -d / 2.0, q)],
+            Self::LinearQuadrupole => vec![at(0.0, -d, q), at(0.0, 0.0, -2.0 * q), at(0.0, d, q)],
+            Self::SquareQuadrupole => {
+                let h = d / 2.0;
+                vec![at(h, h, q), at(h, -h, -q), at(-h, -h, q), at(-h, h, -q)]
+            }
+            Self::Ring => ring(d, q).collect(),
+            Self::ParallelPlates => {
+                // n×n grids in the x–y plane, `d` apart along z, 1.5 d wide
+                let side = 1.5 * d;
+                let step = if n > 1 { side / (n - 1) as f32 } else { 0.0 };
+                let half = step * (n - 1) as f32 / 2.0;
+                let mut out = Vec::with_capacity(2 * n * n);
+                for (z, sign) in [(d / 2.0, 1.0), (-d / 2.0, -1.0)] {
+                    for i in 0..n {
+                        for j in 0..n {
+                            out.push(Charge3D {
+                                pos: Vec3::new(i as f32 * step - half, j as f32 * step - half, z),
+                                q: sign * q / (n * n) as f32,
+                                vel: Vec3::ZERO,
+                            });
+                        }
+                    }
+                }
+                out
+            }
+            // an enclosed charge and the opposite charge induced on the cage,
+            // so the field outside (nearly) cancels
+            Self::FaradayRing => std::iter::once(at(0.0, 0.0, q))
+                .chain(ring(d, -q))
+                .collect(),
+            // the plane is z = 0; the image sits mirrored behind it
+            Self::ImageCharge => vec![at(0.0, d, q), at(0.0, -d, -q)],
+            Self::MovingCharge => vec![Charge3D {
+                pos: Vec3::ZERO,
+                q,
+                vel: Vec3::Z,
+            }],
+        };
+
+        let nearest = nearest_spacing(&charges);
+        let seeding = Seeding {
+            e_per_charge: (120 / charges.len()).clamp(6, 30),
+            shell_radius: nearest.map_or(0.06, |s| (0.3 * s).min(0.06)),
+            ..Seeding::default()
+        };
+        let extent = charges.iter().map(|c| c.pos.length()).fold(0.0, f32::max);
+        PresetScene {
+            charges,
+            seeding,
+            camera: Orbit {
+                yaw: 0.0,
+                pitch: 0.0,
+                radius: 2.5 + 5.0 * extent,
+                target: Vec3::ZERO,
+            },
+        }
+    }
+}
+
+/// Smallest distance between two charges, if there are two.
+fn nearest_spacing(charges: &[Charge3D]) -> Option<f32> {
+    let mut best: Option<f32> = None;
+    for (i, a) in charges.iter().enumerate() {
+        for b in &charges[i + 1..] {
+            let d = a.pos.distance(b.pos);
+            best = Some(best.map_or(d, |m| m.min(d)));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_neutral_or_as_documented() {
+        for preset in Preset::ALL {
+            assert_eq!(Preset::from_id(preset.id()), Some(preset));
+            let scene = preset.build(&preset.defaults());
+            assert!(!scene.charges.is_empty(), "{}", preset.name());
+            let total: f32 = scene.charges.iter().map(|c| c.q).sum();
+            let expected = match preset {
+                Preset::LikePair => 2.0,
+                Preset::Ring | Preset::MovingCharge => 1.0,
+                _ => 0.0,
+            };
+            assert!(
+                (total - expected).abs() < 1e-5,
+                "{}: {total}",
+                preset.name()
+            );
+            assert!(scene.seeding.shell_radius > 0.0);
+        }
+
+        let plates = Preset::ParallelPlates.build(&PresetParams {
+            n: 4,
+            spacing: 0.5,
+            q: 2.0,
+        });
+        assert_eq!(plates.charges.len(), 32);
+        assert!(plates.seeding.shell_radius <= 0.3 * 0.25 + 1e-6);
+    }
+}
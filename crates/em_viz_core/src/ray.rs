@@ -0,0 +1,130 @@
+//! Ray helpers for picking: screen → NDC → world ray, ray/shape hits, and the
+//! screen-space test against charges drawn as fixed-size discs.
+
+use glam::{Mat4, Vec3, Vec4};
+
+/// Client-space point to NDC, given the element's client rect.
+pub fn screen_to_ndc(x: f32, y: f32, left: f32, top: f32, width: f32, height: f32) -> (f32, f32) {
+    let nx = (x - left) / width;
+    let ny = (y - top) / height;
+    (nx * 2.0 - 1.0, 1.0 - ny * 2.0)
+}
+
+pub fn ray_from_ndc_with_inv(ndc: (f32, f32), inv_vp: Mat4, eye: Vec3) -> (Vec3, Vec3) {
+    let p_ndc = Vec4::new(ndc.0, ndc.1, 0.0, 1.0);
+    let q_ndc = Vec4::new(ndc.0, ndc.1, 1.0, 1.0);
+    let p = inv_vp * p_ndc;
+    let q = inv_vp * q_ndc;
+    let p = (p.truncate() / p.w).extend(1.0).truncate();
+    let q = (q.truncate() / q.w).extend(1.0).truncate();
+    let ro = eye;
+    let rd = (q - p).normalize_or_zero();
+    (ro, rd)
+}
+
+pub fn ray_sphere(ro: Vec3, rd: Vec3, c: Vec3, r: f32) -> Option<f32> {
+    let oc = ro - c;
+    let b = oc.dot(rd);
+    let c2 = oc.dot(oc) - r * r;
+    let disc = b * b - c2;
+    if disc < 0.0 {
+        return None;
+    }
+    let t = -b - disc.sqrt();
+    if t > 0.0 { Some(t) } else { None }
+}
+
+pub fn ray_plane(ro: Vec3, rd: Vec3, p0: Vec3, n: Vec3) -> Option<f32> {
+    let denom = rd.dot(n);
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let t = (p0 - ro).dot(n) / denom;
+    if t > 0.0 { Some(t) } else { None }
+}
+
+/// Index of the point under `ndc` when each is drawn as a disc `radius_px`
+/// in radius on a `viewport` of device px, the same size at any distance like
+/// the charge impostors. Where discs overlap the one nearest the camera wins.
+pub fn pick_disc(
+    points: impl IntoIterator<Item = Vec3>,
+    view_proj: Mat4,
+    ndc: (f32, f32),
+    viewport: (f32, f32),
+    radius_px: f32,
+) -> Option<usize> {
+    let px_per_ndc = (viewport.0 * 0.5, viewport.1 * 0.5);
+    points
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, p)| {
+            let clip = view_proj * p.extend(1.0);
+            if clip.w <= 0.0 {
+                return None;
+            }
+            let dx = (clip.x / clip.w - ndc.0) * px_per_ndc.0;
+            let dy = (clip.y / clip.w - ndc.1) * px_per_ndc.1;
+            (dx * dx + dy * dy <= radius_px * radius_px).then_some((i, clip.w))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Camera, Orbit};
+
+    #[test]
+    fn centre_ray_hits_orbit_target() {
+        let mut cam = Camera::new(1.5);
+        cam.update_from_orbit(&Orbit {
+            yaw: 0.7,
+            pitch: 0.3,
+            radius: 4.5,
+            target: Vec3::new(0.2, -0.1, 0.4),
+        });
+        let inv_vp = (cam.proj() * cam.view()).inverse();
+        let ndc = screen_to_ndc(400.0, 300.0, 0.0, 0.0, 800.0, 600.0);
+        let (ro, rd) = ray_from_ndc_with_inv(ndc, inv_vp, cam.eye);
+        let t = ray_sphere(ro, rd, cam.target, 0.1).expect("hit");
+        assert!((t - (4.5 - 0.1)).abs() < 1e-3, "t = {t}");
+        let n = (cam.eye - cam.target).normalize();
+        let t = ray_plane(ro, rd, cam.target, n).expect("hit");
+        assert!((ro + rd * t).distance(cam.target) < 1e-3);
+    }
+
+    #[test]
+    fn disc_pick_is_screen_sized_and_nearest_first() {
+        let mut cam = Camera::new(1.0);
+        let orbit = |radius| Orbit {
+            yaw: 0.0,
+            pitch: 0.0,
+            radius,
+            target: Vec3::ZERO,
+        };
+        let towards_eye = |cam: &Camera| (cam.eye - cam.target).normalize();
+        let viewport = (600.0, 600.0);
+
+        cam.update_from_orbit(&orbit(5.0));
+        let vp = cam.proj() * cam.view();
+        let near = towards_eye(&cam);
+        // two charges on the centre ray: the one in front wins
+        let beside = near.cross(Vec3::Y).normalize() * 0.3;
+        let pts = [Vec3::ZERO, near, beside];
+        assert_eq!(pick_disc(pts, vp, (0.0, 0.0), viewport, 8.0), Some(1));
+
+        // zoomed far out, a neighbour 0.3 away is a few px off: a world-space
+        // radius would swallow it, 8 px does not reach it
+        cam.update_from_orbit(&orbit(60.0));
+        let vp = cam.proj() * cam.view();
+        let side = vp.project_point3(pts[2]);
+        let gap_px = side.x * viewport.0 * 0.5;
+        assert!(gap_px.abs() > 1.0 && gap_px.abs() < 8.0, "{gap_px}");
+        assert_eq!(
+            pick_disc(pts, vp, (side.x, side.y), viewport, 0.5 * gap_px.abs()),
+            Some(2)
+        );
+        assert_eq!(pick_disc(pts, vp, (0.5, 0.5), viewport, 8.0), None);
+    }
+}
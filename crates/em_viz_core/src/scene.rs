@@ -0,0 +1,161 @@
+//! Scene description shared by the web app and the CLI: charges plus how to seed
+//! and integrate field lines. With the `serde` feature it (de)serializes as JSON;
+//! every section and field is optional and falls back to the web app's defaults.
+
+use crate::em3d::{Charge3D, FieldEval, sample_b3d, sample_v3d};
+use crate::grid::{FieldSamples, scene_bounds};
+use crate::seed;
+use crate::trace::{self, LinePoint};
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Seeding {
+    /// E seeds on a shell around each charge.
+    pub e_per_charge: usize,
+    pub shell_radius: f32,
+    /// B seeds on rings around each moving charge.
+    pub b_rings: usize,
+    pub b_per_ring: usize,
+    pub b_radius: f32,
+}
+
+impl Default for Seeding {
+    fn default() -> Self {
+        Self {
+            e_per_charge: 30,
+            shell_radius: 0.06,
+            b_rings: 2,
+            b_per_ring: 16,
+            b_radius: 0.15,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Integrator {
+    /// RK4 step length.
+    pub h: f32,
+    /// Steps per line.
+    pub max_pts: u32,
+    /// Coulomb constant.
+    pub k: f32,
+    /// Softening length squared.
+    pub soft2: f32,
+    /// 1/c², scales B.
+    pub c_inv2: f32,
+    pub eval: FieldEval,
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Self {
+            h: 0.015,
+            max_pts: 400,
+            k: 1.0,
+            soft2: 0.0025,
+            c_inv2: 1.0,
+            eval: FieldEval::Direct,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Scene {
+    pub charges: Vec<Charge3D>,
+    pub seeding: Seeding,
+    pub integrator: Integrator,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    E,
+    B,
+}
+
+impl LineKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::E => "E",
+            Self::B => "B",
+        }
+    }
+}
+
+/// One traced field line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldLine {
+    pub kind: LineKind,
+    pub points: Vec<LinePoint>,
+}
+
+impl Scene {
+    /// Electric potential V at `p`.
+    pub fn potential(&self, p: glam::Vec3) -> f32 {
+        sample_v3d(p, &self.charges, self.integrator.k, self.integrator.soft2)
+    }
+
+    /// E, V (and B if anything moves) on a `res`³ grid over [`scene_bounds`].
+    pub fn sample_grid(&self, res: usize) -> FieldSamples {
+        let it = &self.integrator;
+        FieldSamples::sample(
+            &self.charges,
+            scene_bounds(&self.charges),
+            [res; 3],
+            it.k,
+            it.soft2,
+            it.c_inv2,
+        )
+    }
+
+    pub fn e_seeds(&self) -> Vec<[f32; 4]> {
+        seed::e_seeds(
+            &self.charges,
+            self.seeding.e_per_charge,
+            self.seeding.shell_radius,
+        )
+    }
+
+    pub fn b_seeds(&self) -> Vec<[f32; 4]> {
+        seed::b_seeds(
+            &self.charges,
+            self.seeding.b_radius,
+            self.seeding.b_rings,
+            self.seeding.b_per_ring,
+        )
+    }
+
+    /// Trace lines of `kind` with the same integrator as the compute shader.
+    /// B lines only exist around charges with a non-zero velocity.
+    pub fn trace(&self, kind: LineKind) -> Vec<FieldLine> {
+        let it = &self.integrator;
+        let lines = |seeds: Vec<[f32; 4]>, field: &dyn Fn(glam::Vec3) -> glam::Vec3| {
+            seeds
+                .iter()
+                .map(|s| FieldLine {
+                    kind,
+                    points: trace::trace_line(
+                        field,
+                        glam::Vec3::new(s[0], s[1], s[2]),
+                        s[3],
+                        it.h,
+                        it.max_pts,
+                    ),
+                })
+                .collect()
+        };
+        match kind {
+            LineKind::E => lines(
+                self.e_seeds(),
+                &trace::e_field(&self.charges, it.eval, it.k, it.soft2),
+            ),
+            LineKind::B => lines(self.b_seeds(), &|p| {
+                sample_b3d(p, &self.charges, it.k, it.soft2, it.c_inv2)
+            }),
+        }
+    }
+}
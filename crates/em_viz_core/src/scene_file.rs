@@ -0,0 +1,219 @@
+//! Versioned scene files, read and written by both the web app and the CLI.
+//! A file holds a [`Scene`] plus the web app's view state (layers, point size,
+//! colormaps, field grid, camera, motion). Every section is optional, so
+//! hand-written files can stay short; a file without a `version` is version 1.
+//!
+//! Versions:
+//! - 1: the current format. Scenes written for the CLI before saving existed
+//!   are version 1 files with only `charges`, `seeding` and `integrator`.
+//!
+//! A change that renames or reinterprets a field bumps [`VERSION`] and adds
+//! the upgrade from the previous version to [`SceneFile::parse`].
+
+use crate::anim::Motion;
+use crate::camera::Orbit;
+use crate::colormap::Colormaps;
+use crate::em3d::Charge3D;
+use crate::scene::{Integrator, Scene, Seeding};
+use serde::{Deserialize, Serialize};
+
+/// Version written by this build; files up to this version can be read.
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    pub charges: Vec<Charge3D>,
+    pub seeding: Seeding,
+    pub integrator: Integrator,
+    pub layers: Layers,
+    pub display: Display,
+    /// Camera to restore; without one the current view is kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<Orbit>,
+    pub motion: Motion,
+}
+
+/// Files without a `version` field are the first version.
+fn unversioned() -> u32 {
+    1
+}
+
+impl Default for SceneFile {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            charges: Vec::new(),
+            seeding: Seeding::default(),
+            integrator: Integrator::default(),
+            layers: Layers::default(),
+            display: Display::default(),
+            camera: None,
+            motion: Motion::default(),
+        }
+    }
+}
+
+/// Which line layers are shown.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layers {
+    pub e: bool,
+    /// B lines; only moving charges have any.
+    pub b: bool,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self { e: true, b: true }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Display {
+    /// Charge impostor diameter.
+    pub point_size_px: f32,
+    pub colormaps: Colormaps,
+    /// Voxels per axis of the grid E is baked into before tracing; without
+    /// one the exact field is traced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid_res: Option<u32>,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self {
+            point_size_px: 14.0,
+            colormaps: Colormaps::default(),
+            grid_res: None,
+        }
+    }
+}
+
+/// Text syntax of a scene file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    Json,
+    #[cfg(feature = "ron")]
+    Ron,
+}
+
+impl Syntax {
+    /// From a file name's extension; anything but `.ron` is JSON.
+    pub fn from_name(name: &str) -> Self {
+        match name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+        {
+            #[cfg(feature = "ron")]
+            Some(ext) if ext == "ron" => Self::Ron,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Just enough to check the version before parsing the rest.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default = "unversioned")]
+    version: u32,
+}
+
+impl SceneFile {
+    pub fn new(scene: Scene) -> Self {
+        Self {
+            charges: scene.charges,
+            seeding: scene.seeding,
+            integrator: scene.integrator,
+            ..Self::default()
+        }
+    }
+
+    pub fn scene(&self) -> Scene {
+        Scene {
+            charges: self.charges.clone(),
+            seeding: self.seeding.clone(),
+            integrator: self.integrator.clone(),
+        }
+    }
+
+    /// Parse a file of any version up to [`VERSION`].
+    pub fn parse(text: &str, syntax: Syntax) -> Result<Self, String> {
+        let header: Header = from_text(text, syntax)?;
+        match header.version {
+            0 => return Err("invalid scene version 0".into()),
+            v if v > VERSION => {
+                return Err(format!(
+                    "scene version {v} is newer than this build reads ({VERSION})"
+                ));
+            }
+            _ => {}
+        }
+        from_text(text, syntax)
+    }
+
+    pub fn to_text(&self, syntax: Syntax) -> String {
+        match syntax {
+            Syntax::Json => serde_json::to_string_pretty(self).expect("scene serializes"),
+            #[cfg(feature = "ron")]
+            Syntax::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .expect("scene serializes"),
+        }
+    }
+}
+
+fn from_text<T: for<'de> Deserialize<'de>>(text: &str, syntax: Syntax) -> Result<T, String> {
+    match syntax {
+        Syntax::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+        #[cfg(feature = "ron")]
+        Syntax::Ron => ron::from_str(text).map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colormap::Colormap;
+    use glam::Vec3;
+
+    #[test]
+    fn reads_unversioned_files_and_round_trips() {
+        let cli = r#"{
+            "charges": [{ "pos": [0.4, 0.0, 0.0], "q": -1.0 }],
+            "seeding": { "e_per_charge": 24 },
+            "integrator": { "eval": { "barnes_hut": { "theta": 0.7 } } }
+        }"#;
+        let file = SceneFile::parse(cli, Syntax::Json).unwrap();
+        assert_eq!(file.version, VERSION);
+        assert_eq!(file.charges[0].vel, Vec3::ZERO);
+        assert_eq!(file.seeding.e_per_charge, 24);
+        assert_eq!(file.seeding.shell_radius, Seeding::default().shell_radius);
+        assert_eq!(file.layers, Layers::default());
+        assert_eq!(file.display.grid_res, None);
+        assert_eq!(file.camera, None);
+
+        let mut file = file;
+        file.camera = Some(Orbit {
+            yaw: 0.5,
+            pitch: -0.2,
+            radius: 3.0,
+            target: Vec3::new(0.0, 1.0, 0.0),
+        });
+        file.motion.orbit_deg_per_s = 10.0;
+        file.display.grid_res = Some(96);
+        file.display.colormaps.e = Colormap::Plasma;
+        let json = file.to_text(Syntax::Json);
+        assert_eq!(SceneFile::parse(&json, Syntax::Json).unwrap(), file);
+        #[cfg(feature = "ron")]
+        {
+            let ron = file.to_text(Syntax::Ron);
+            assert_eq!(SceneFile::parse(&ron, Syntax::Ron).unwrap(), file);
+        }
+
+        let newer = format!(r#"{{ "version": {} }}"#, VERSION + 1);
+        assert!(SceneFile::parse(&newer, Syntax::Json).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::em3d::Charge3D;
 use glam::Quat;
 use glam::Vec3;
 
@@ -66,3 +67,30 @@ pub fn b_rings_for_charge(
     }
     out
 }
+
+/// E seeds on a small shell around every charge: `xyz` = start, `w` = +1 to
+/// follow E away from positive charges, -1 to follow it back into negative ones.
+pub fn e_seeds(charges: &[Charge3D], per_charge: usize, shell_radius: f32) -> Vec<[f32; 4]> {
+    let mut seeds = Vec::with_capacity(charges.len() * per_charge);
+    for c in charges {
+        let sign = if c.q >= 0.0 { 1.0 } else { -1.0 };
+        for s0 in fibonacci_sphere(c.pos, shell_radius, per_charge) {
+            seeds.push([s0.x, s0.y, s0.z, sign]);
+        }
+    }
+    seeds
+}
+
+/// B seeds on rings around every moving charge, same packing as [`e_seeds`].
+pub fn b_seeds(
+    charges: &[Charge3D],
+    base_radius: f32,
+    rings: usize,
+    pts_per_ring: usize,
+) -> Vec<[f32; 4]> {
+    charges
+        .iter()
+        .flat_map(|c| b_rings_for_charge(c.pos, c.vel, base_radius, rings, pts_per_ring))
+        .map(|p| [p.x, p.y, p.z, 1.0])
+        .collect()
+}
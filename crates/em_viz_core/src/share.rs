@@ -0,0 +1,81 @@
+//! Scene files packed into a URL fragment, for links that open a scene.
+//!
+//! A link payload is `<encoding>.<data>`. Encoding 1 is compact JSON of a
+//! [`SceneFile`], deflated and base64url-encoded without padding. The JSON
+//! carries the scene version, so old links are read like old files; a new
+//! encoding gets a new number and the old ones keep decoding.
+
+use crate::scene_file::{SceneFile, Syntax};
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+/// Encoding written by [`encode`].
+pub const ENCODING: u32 = 1;
+
+/// Refuse payloads that inflate past this, whatever the link says.
+const MAX_JSON_BYTES: usize = 16 << 20;
+
+pub fn encode(file: &SceneFile) -> String {
+    let json = serde_json::to_string(file).expect("scene serializes");
+    pack(&json)
+}
+
+pub fn decode(payload: &str) -> Result<SceneFile, String> {
+    let (encoding, data) = payload
+        .split_once('.')
+        .ok_or("not a scene link (no encoding prefix)")?;
+    match encoding {
+        "1" => {
+            let deflated = URL_SAFE_NO_PAD
+                .decode(data)
+                .map_err(|e| format!("bad base64: {e}"))?;
+            let json =
+                miniz_oxide::inflate::decompress_to_vec_with_limit(&deflated, MAX_JSON_BYTES)
+                    .map_err(|e| format!("bad compressed data: {e}"))?;
+            let json = String::from_utf8(json).map_err(|e| e.to_string())?;
+            SceneFile::parse(&json, Syntax::Json)
+        }
+        _ => Err(format!(
+            "scene link encoding {encoding} is newer than this build reads ({ENCODING})"
+        )),
+    }
+}
+
+fn pack(json: &str) -> String {
+    let deflated = miniz_oxide::deflate::compress_to_vec(json.as_bytes(), 9);
+    format!("{ENCODING}.{}", URL_SAFE_NO_PAD.encode(deflated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::Preset;
+    use crate::scene_file::VERSION;
+
+    #[test]
+    fn links_round_trip() {
+        let preset = Preset::ParallelPlates.build(&Preset::ParallelPlates.defaults());
+        let file = SceneFile {
+            charges: preset.charges,
+            seeding: preset.seeding,
+            camera: Some(preset.camera),
+            ..SceneFile::default()
+        };
+        let link = encode(&file);
+        assert!(link.starts_with("1."));
+        assert!(
+            link.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b))
+        );
+        assert_eq!(decode(&link).unwrap(), file);
+
+        // a link to a hand-written scene without a version still opens
+        let bare = pack(r#"{"charges":[{"pos":[0,0,0.4],"q":1}]}"#);
+        let opened = decode(&bare).unwrap();
+        assert_eq!(opened.version, VERSION);
+        assert_eq!(opened.charges.len(), 1);
+
+        assert!(decode("2.AAAA").is_err());
+        assert!(decode("1.not*base64").is_err());
+    }
+}
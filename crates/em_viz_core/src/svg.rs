@@ -0,0 +1,258 @@
+//! SVG export of the projected 2D view. Lines and charges go through the same
+//! view-projection as the renderers and are painted back to front, so charges
+//! hide the parts of lines that pass behind them.
+
+use crate::colormap::Colormaps;
+use crate::em3d::Charge3D;
+use crate::scene::FieldLine;
+use crate::trace::tone_from_mag;
+use glam::{Mat4, Vec3};
+use std::fmt::Write as _;
+
+/// Ribbon opacity used by the renderers.
+pub const LINE_OPACITY: f32 = 0.85;
+
+/// Segments per `<polyline>`. Each run gets one colour and one depth, so
+/// shorter runs follow the palette and the occlusion more closely.
+const RUN_SEGMENTS: usize = 6;
+
+/// The view to draw: `view_proj` is `proj * view` of the on-screen camera and
+/// the sizes are in output pixels, like the canvas.
+#[derive(Clone, Copy, Debug)]
+pub struct SvgView {
+    pub view_proj: Mat4,
+    pub width: u32,
+    pub height: u32,
+    /// Stroke width of the field lines.
+    pub line_px: f32,
+    /// Diameter of the charge discs.
+    pub charge_px: f32,
+    /// Palette of each line layer.
+    pub colormaps: Colormaps,
+    /// RGB (0..1) fill behind everything; `None` leaves it transparent.
+    pub background: Option<[f32; 3]>,
+}
+
+impl SvgView {
+    /// Pixel position and NDC depth, or `None` outside the near / far planes.
+    fn project(&self, p: Vec3) -> Option<(f32, f32, f32)> {
+        let clip = self.view_proj * p.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        if !(-1.0..=1.0).contains(&ndc.z) {
+            return None;
+        }
+        Some((
+            (ndc.x + 1.0) * 0.5 * self.width as f32,
+            (1.0 - ndc.y) * 0.5 * self.height as f32,
+            ndc.z,
+        ))
+    }
+
+    /// Whether anything of `pts` (with a margin for stroke and disc size) can
+    /// land inside the viewport.
+    fn on_screen(&self, pts: &[(f32, f32, f32)]) -> bool {
+        let m = self.line_px.max(self.charge_px);
+        let (lo, hi) = pts.iter().fold(
+            (
+                (f32::INFINITY, f32::INFINITY),
+                (f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(lo, hi), p| {
+                (
+                    (lo.0.min(p.0), lo.1.min(p.1)),
+                    (hi.0.max(p.0), hi.1.max(p.1)),
+                )
+            },
+        );
+        hi.0 >= -m && hi.1 >= -m && lo.0 <= self.width as f32 + m && lo.1 <= self.height as f32 + m
+    }
+}
+
+/// Something to paint, with the NDC depth it is sorted by.
+enum Item {
+    Run {
+        pts: Vec<(f32, f32, f32)>,
+        color: [f32; 3],
+    },
+    Charge {
+        at: (f32, f32, f32),
+        q: f32,
+    },
+}
+
+impl Item {
+    fn depth(&self) -> f32 {
+        match self {
+            Self::Run { pts, .. } => pts.iter().map(|p| p.2).sum::<f32>() / pts.len() as f32,
+            Self::Charge { at, .. } => at.2,
+        }
+    }
+}
+
+/// Charges as shaded discs with a +/− glyph and lines as polylines in the
+/// palette of their kind, painted far to near.
+pub fn scene_svg(charges: &[Charge3D], lines: &[FieldLine], view: &SvgView) -> String {
+    let mut items = Vec::new();
+    for line in lines {
+        let palette = view.colormaps.of(line.kind);
+        let projected: Vec<_> = line
+            .points
+            .iter()
+            .map(|pt| view.project(pt.pos).map(|p| (p, tone_from_mag(pt.mag))))
+            .collect();
+        // near / far plane crossings split the line; consecutive runs share an end
+        for piece in projected.split(Option::is_none) {
+            let piece: Vec<_> = piece.iter().flatten().copied().collect();
+            let mut start = 0;
+            while start + 1 < piece.len() {
+                let end = (start + RUN_SEGMENTS).min(piece.len() - 1);
+                let run = &piece[start..=end];
+                let pts: Vec<_> = run.iter().map(|r| r.0).collect();
+                if view.on_screen(&pts) {
+                    let tone = run.iter().map(|r| r.1).sum::<f32>() / run.len() as f32;
+                    items.push(Item::Run {
+                        pts,
+                        color: palette.color(tone),
+                    });
+                }
+                start = end;
+            }
+        }
+    }
+    for c in charges {
+        if let Some(at) = view.project(c.pos)
+            && view.on_screen(&[at])
+        {
+            items.push(Item::Charge { at, q: c.q });
+        }
+    }
+    // GL depth: larger NDC z is farther away
+    items.sort_by(|a, b| b.depth().total_cmp(&a.depth()));
+
+    let (w, h) = (view.width, view.height);
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#
+    );
+    // lit like the impostor shader: light from the upper right, 0.5..1 diffuse
+    let _ = writeln!(
+        svg,
+        concat!(
+            r#"<defs><radialGradient id="charge" fx="0.62" fy="0.34">"#,
+            r#"<stop offset="0" stop-color="{}"/><stop offset="1" stop-color="{}"/>"#,
+            r#"</radialGradient></defs>"#
+        ),
+        hex([0.95; 3]),
+        hex([0.5; 3])
+    );
+    if let Some(bg) = view.background {
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            hex(bg)
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r#"<g fill="none" stroke-width="{:.2}" stroke-linecap="round" stroke-linejoin="round" stroke-opacity="{LINE_OPACITY}">"#,
+        view.line_px
+    );
+    let r = view.charge_px * 0.5;
+    for item in &items {
+        match item {
+            Item::Run { pts, color } => {
+                svg.push_str(r#"<polyline points=""#);
+                for (i, p) in pts.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { " " };
+                    let _ = write!(svg, "{sep}{:.1},{:.1}", p.0, p.1);
+                }
+                let _ = writeln!(svg, r#"" stroke="{}"/>"#, hex(*color));
+            }
+            Item::Charge { at, q } => {
+                let (x, y) = (at.0, at.1);
+                let _ = write!(
+                    svg,
+                    r##"<circle cx="{x:.1}" cy="{y:.1}" r="{r:.2}" fill="url(#charge)" stroke="none"/>"##
+                );
+                if *q != 0.0 {
+                    let a = r * 0.5;
+                    let _ = write!(svg, r##"<path d="M{:.1} {y:.1}H{:.1}"##, x - a, x + a);
+                    if *q > 0.0 {
+                        let _ = write!(svg, "M{x:.1} {:.1}V{:.1}", y - a, y + a);
+                    }
+                    let _ = write!(
+                        svg,
+                        r##"" stroke="#202020" stroke-width="{:.2}" stroke-opacity="1" stroke-linecap="butt"/>"##,
+                        r * 0.2
+                    );
+                }
+                svg.push('\n');
+            }
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// `#rrggbb` of a colour in 0..1.
+fn hex(c: [f32; 3]) -> String {
+    let [r, g, b] = c.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::scene::LineKind;
+    use crate::trace::LinePoint;
+
+    #[test]
+    fn paints_back_to_front_and_clips_behind_camera() {
+        let mut cam = Camera::new(1.0);
+        cam.eye = Vec3::new(0.0, 0.0, 2.0);
+        let view = SvgView {
+            view_proj: cam.proj() * cam.view(),
+            width: 200,
+            height: 200,
+            line_px: 4.0,
+            charge_px: 14.0,
+            colormaps: Colormaps::default(),
+            background: None,
+        };
+        // a line from behind the charge to behind the camera
+        let line = FieldLine {
+            kind: LineKind::E,
+            points: (0..20)
+                .map(|i| LinePoint {
+                    pos: Vec3::new(0.0, 0.0, -1.0 + 0.25 * i as f32),
+                    tangent: Vec3::Z,
+                    mag: 1.0,
+                })
+                .collect(),
+        };
+        let charges = [Charge3D {
+            pos: Vec3::ZERO,
+            q: -1.0,
+            vel: Vec3::ZERO,
+        }];
+        let svg = scene_svg(&charges, &[line], &view);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert!(!svg.contains("<rect"));
+
+        let first_line = svg.find("<polyline").unwrap();
+        let charge = svg.find("<circle").unwrap();
+        let last_line = svg.rfind("<polyline").unwrap();
+        assert!(first_line < charge && charge < last_line, "{svg}");
+        // minus glyph only
+        assert!(svg.contains(r#"<path d="M96.5 100.0H103.5""#), "{svg}");
+        assert!(!svg.contains('V'));
+        // nothing at or behind the eye was projected
+        assert!(!svg.contains("NaN") && !svg.contains("inf"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+    }
+}
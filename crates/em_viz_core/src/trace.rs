@@ -0,0 +1,200 @@
+//! CPU field-line tracer. Mirrors `main` in `ribbon_e_comp.wgsl` step for step so
+//! renderers without compute shaders (WebGL2) draw the same ribbons.
+
+use crate::em3d::octree::Octree;
+use crate::em3d::{Charge3D, FieldEval, sample_b3d, sample_e3d};
+use glam::Vec3;
+
+/// One ribbon vertex, packed like the compute output: `(center, side)` then
+/// `(tangent, tone)`.
+pub type RibbonVertex = [f32; 8];
+
+/// Traced ribbons as triangle strips in one vertex array.
+#[derive(Clone, Debug, Default)]
+pub struct Ribbons {
+    pub verts: Vec<RibbonVertex>,
+    pub strips: Vec<(u32, u32)>, // (first vertex, vertex count) per seed
+}
+
+fn dir_of(e: Vec3, sign: f32) -> Vec3 {
+    if e.length() > 1e-6 {
+        e.normalize() * sign
+    } else {
+        Vec3::ZERO
+    }
+}
+
+/// Maps a field magnitude to the 0..1 palette coordinate used by the ribbons.
+pub fn tone_from_mag(m: f32) -> f32 {
+    (m / (1.0 + m)).powf(0.75)
+}
+
+/// One traced sample: position, unit tangent and field magnitude at the step's
+/// start point (what the GPU tone is derived from).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinePoint {
+    pub pos: Vec3,
+    pub tangent: Vec3,
+    pub mag: f32,
+}
+
+/// RK4-trace the line of `field` through `start`, following `sign * field`, for
+/// up to `max_pts` steps of length `h`. Stops early once |field| leaves
+/// [1e-6, 1e4], exactly like the compute shader.
+pub fn trace_line(
+    field: impl Fn(Vec3) -> Vec3,
+    start: Vec3,
+    sign: f32,
+    h: f32,
+    max_pts: u32,
+) -> Vec<LinePoint> {
+    let mut out = Vec::new();
+    let mut p = start;
+    let mut prev = p + Vec3::Z;
+    for _ in 0..max_pts {
+        let e1 = field(p);
+        let m1 = e1.length();
+        let k1 = dir_of(e1, sign);
+        let k2 = dir_of(field(p + 0.5 * h * k1), sign);
+        let k3 = dir_of(field(p + 0.5 * h * k2), sign);
+        let k4 = dir_of(field(p + h * k3), sign);
+        let p2 = p + h * (k1 + 2.0 * k2 + 2.0 * k3 + k4) / 6.0;
+
+        let tangent = (p2 - prev).normalize_or_zero();
+        prev = p;
+        p = p2;
+        out.push(LinePoint {
+            pos: p,
+            tangent,
+            mag: m1,
+        });
+
+        if !(1e-6..=1e4).contains(&m1) {
+            break;
+        }
+    }
+    out
+}
+
+/// E field sampler for `eval`; Barnes–Hut builds its octree once up front.
+pub fn e_field(
+    charges: &[Charge3D],
+    eval: FieldEval,
+    k: f32,
+    soft2: f32,
+) -> impl Fn(Vec3) -> Vec3 + '_ {
+    let tree = match eval {
+        FieldEval::BarnesHut { theta } => {
+            Some((Octree::build(charges, Octree::DEFAULT_LEAF_SIZE), theta))
+        }
+        FieldEval::Direct | FieldEval::Tiled => None,
+    };
+    move |p| match &tree {
+        Some((tree, theta)) => tree.sample_e(p, k, soft2, *theta),
+        None => sample_e3d(p, charges, k, soft2),
+    }
+}
+
+/// RK4-trace one E line per seed (`xyz` = start, `w` = direction sign) and pack
+/// them as ribbon strips.
+pub fn trace_e_ribbons(
+    charges: &[Charge3D],
+    seeds: &[[f32; 4]],
+    h: f32,
+    max_pts: u32,
+    eval: FieldEval,
+    k: f32,
+    soft2: f32,
+) -> Ribbons {
+    trace_ribbons(e_field(charges, eval, k, soft2), seeds, h, max_pts)
+}
+
+/// B counterpart of [`trace_e_ribbons`] for the seeds from
+/// [`crate::seed::b_seeds`]. There is no compute shader for B; every renderer
+/// draws these.
+pub fn trace_b_ribbons(
+    charges: &[Charge3D],
+    seeds: &[[f32; 4]],
+    h: f32,
+    max_pts: u32,
+    k: f32,
+    soft2: f32,
+    c_inv2: f32,
+) -> Ribbons {
+    let field = |p| sample_b3d(p, charges, k, soft2, c_inv2);
+    trace_ribbons(field, seeds, h, max_pts)
+}
+
+fn trace_ribbons(
+    field: impl Fn(Vec3) -> Vec3,
+    seeds: &[[f32; 4]],
+    h: f32,
+    max_pts: u32,
+) -> Ribbons {
+    let mut out = Ribbons::default();
+    for seed in seeds {
+        let first = out.verts.len() as u32;
+        let start = Vec3::new(seed[0], seed[1], seed[2]);
+        for pt in trace_line(&field, start, seed[3], h, max_pts) {
+            let (p, t) = (pt.pos, pt.tangent);
+            let tone = tone_from_mag(pt.mag);
+            for side in [-1.0, 1.0] {
+                out.verts.push([p.x, p.y, p.z, side, t.x, t.y, t.z, tone]);
+            }
+        }
+        out.strips.push((first, out.verts.len() as u32 - first));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_charge_traces_radial_lines() {
+        let charges = [Charge3D {
+            pos: Vec3::ZERO,
+            q: 1.0,
+            vel: Vec3::ZERO,
+        }];
+        let seeds = [[0.1, 0.0, 0.0, 1.0], [0.0, -0.1, 0.0, 1.0]];
+        let r = trace_e_ribbons(&charges, &seeds, 0.01, 50, FieldEval::Direct, 1.0, 0.0);
+        assert_eq!(r.strips, vec![(0, 100), (100, 100)]);
+        let last = r.verts[99];
+        assert!(
+            (last[0] - 0.6).abs() < 1e-3 && last[1].abs() < 1e-5,
+            "{last:?}"
+        );
+        let last = r.verts[199];
+        assert!(
+            (last[1] + 0.6).abs() < 1e-3 && last[0].abs() < 1e-5,
+            "{last:?}"
+        );
+    }
+
+    #[test]
+    fn moving_charge_traces_b_rings() {
+        let charges = [Charge3D {
+            pos: Vec3::ZERO,
+            q: 1.0,
+            vel: Vec3::Z,
+        }];
+        let seeds = crate::seed::b_seeds(&charges, 0.2, 1, 4);
+        assert_eq!(seeds.len(), 4);
+        let r = trace_b_ribbons(&charges, &seeds, 0.01, 100, 1.0, 0.0, 1.0);
+        assert_eq!(r.strips.len(), 4);
+        // circles about the velocity through each seed
+        for v in &r.verts {
+            let p = Vec3::new(v[0], v[1], v[2]);
+            assert!(p.z.abs() < 1e-4, "{p:?}");
+            assert!((p.truncate().length() - 0.2).abs() < 1e-3, "{p:?}");
+        }
+
+        let still = [Charge3D {
+            vel: Vec3::ZERO,
+            ..charges[0]
+        }];
+        assert!(crate::seed::b_seeds(&still, 0.2, 1, 4).is_empty());
+    }
+}
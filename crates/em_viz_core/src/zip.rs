@@ -0,0 +1,133 @@
+//! Minimal zip writer for frame sequences. Entries are stored uncompressed,
+//! since PNGs are already deflated, and there is no zip64, so archives stop
+//! at 4 GiB.
+
+/// Accumulates stored entries in memory; `finish` appends the central directory.
+#[derive(Default)]
+pub struct ZipWriter {
+    out: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIR: u32 = 0x0605_4b50;
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes written so far (without the central directory).
+    pub fn len(&self) -> usize {
+        self.out.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let offset = self.out.len();
+        let end = offset + 30 + name.len() + data.len();
+        if end > u32::MAX as usize || self.entries == u16::MAX {
+            return Err("zip archive would exceed 4 GiB or 65535 entries".into());
+        }
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        // local file header: version 1.0, no flags, stored, zero DOS time/date
+        put32(&mut self.out, LOCAL_HEADER);
+        for v in [10u16, 0, 0, 0, 0] {
+            put16(&mut self.out, v);
+        }
+        put32(&mut self.out, crc);
+        put32(&mut self.out, size);
+        put32(&mut self.out, size);
+        put16(&mut self.out, name.len() as u16);
+        put16(&mut self.out, 0);
+        self.out.extend_from_slice(name.as_bytes());
+        self.out.extend_from_slice(data);
+
+        put32(&mut self.central, CENTRAL_HEADER);
+        for v in [10u16, 10, 0, 0, 0, 0] {
+            put16(&mut self.central, v);
+        }
+        put32(&mut self.central, crc);
+        put32(&mut self.central, size);
+        put32(&mut self.central, size);
+        // name length, extra, comment, disk, internal attrs
+        for v in [name.len() as u16, 0, 0, 0, 0] {
+            put16(&mut self.central, v);
+        }
+        put32(&mut self.central, 0); // external attrs
+        put32(&mut self.central, offset as u32);
+        self.central.extend_from_slice(name.as_bytes());
+
+        self.entries += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let dir_offset = self.out.len() as u32;
+        let dir_len = self.central.len() as u32;
+        self.out.append(&mut self.central);
+        put32(&mut self.out, END_OF_CENTRAL_DIR);
+        for v in [0u16, 0, self.entries, self.entries] {
+            put16(&mut self.out, v);
+        }
+        put32(&mut self.out, dir_len);
+        put32(&mut self.out, dir_offset);
+        put16(&mut self.out, 0); // comment length
+        self.out
+    }
+}
+
+fn put16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+/// CRC-32 (IEEE), bitwise; frames are few enough that a table isn't worth it.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc_matches_reference() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn archive_layout() {
+        let mut zip = ZipWriter::new();
+        zip.add("a.txt", b"hello").unwrap();
+        zip.add("b.txt", b"").unwrap();
+        let bytes = zip.finish();
+        assert_eq!(&bytes[..4], &LOCAL_HEADER.to_le_bytes());
+        let eocd = bytes.len() - 22;
+        assert_eq!(&bytes[eocd..eocd + 4], &END_OF_CENTRAL_DIR.to_le_bytes());
+        let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let (dir_len, dir_offset) = (word(eocd + 12) as usize, word(eocd + 16) as usize);
+        assert_eq!(dir_offset + dir_len, eocd);
+        assert_eq!(bytes[eocd + 10], 2);
+        assert_eq!(word(dir_offset), CENTRAL_HEADER);
+        assert_eq!(&bytes[30..35], b"a.txt");
+        assert_eq!(&bytes[35..40], b"hello");
+    }
+}
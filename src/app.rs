@@ -1,12 +1,30 @@
+use crate::capture::{self, CaptureOptions};
+use crate::download;
+use crate::export;
+use crate::field_grid::{FieldSource, GridSettings};
+use crate::gizmo;
+use crate::gpu_errors::ErrorSink;
+use crate::history;
 use crate::perf::{self, Scope};
+use crate::record::{self, RecordFormat, RecordOptions};
+use crate::renderer::Renderer;
+use crate::scene_io;
+use crate::share_link;
 use crate::state::AppState;
-use crate::wgpu_renderer::WgpuRenderer;
+use crate::wgpu_renderer::CAPTURE_MSAA;
+use crate::workspaces;
 use crate::{camera, picking};
+use em_viz_core::colormap::Colormap;
+use em_viz_core::em3d::{Charge3D, FieldEval};
+use em_viz_core::group::GroupOp;
+use em_viz_core::presets::Preset;
+use em_viz_core::scene::{Integrator, LineKind};
+use em_viz_core::scene_file::Syntax;
 use glam::Vec3;
 use leptos::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::closure::Closure;
-use web_sys::HtmlInputElement;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
 
 #[component]
 pub fn App() -> impl IntoView {
@@ -15,28 +33,265 @@ pub fn App() -> impl IntoView {
 
 #[component]
 fn FieldCanvas() -> impl IntoView {
-    let charges3d = vec![
-        crate::em3d::Charge3D {
-            pos: Vec3::new(0.0, 0.0, 0.4),
-            q: 1.0,
-            vel: Vec3::ZERO,
-        },
-        crate::em3d::Charge3D {
-            pos: Vec3::new(0.0, 0.0, -0.4),
-            q: -1.0,
-            vel: Vec3::ZERO,
-        },
-    ];
+    let start = Preset::Dipole;
+    let preset = RwSignal::new(start);
+    let preset_params = RwSignal::new(start.defaults());
+    let charges3d = start.build(&start.defaults()).charges;
 
     let app = AppState::new(charges3d, 14.0);
+    let scene_error = RwSignal::new(None::<String>);
+    let linked = share_link::attach(app, scene_error);
+    history::attach_keys(app);
+    // a link wins over the last session; until the prompt is answered the
+    // autosave is left alone
+    let restore_prompt = RwSignal::new(!linked && workspaces::autosaved().is_some());
+    workspaces::attach_autosave(app, Signal::derive(move || !restore_prompt.get()));
+    let workspace_names = RwSignal::new(workspaces::list());
+    let workspace_name = RwSignal::new(String::new());
+    let workspace_error = RwSignal::new(None::<String>);
+    let workspace_result = move |r: anyhow::Result<()>| {
+        workspace_error.set(r.err().map(|e| e.to_string()));
+        workspace_names.set(workspaces::list());
+    };
+    let export_error = RwSignal::new(None::<String>);
+    let run_export = move |f: fn(&AppState) -> anyhow::Result<()>| {
+        export_error.set(f(&app).err().map(|e| format!("export failed: {e}")));
+    };
 
     view! {
       <main class="h-screen flex overflow-hidden">
         <aside class="w-96 h-full overflow-y-auto p-4 border-r border-zinc-800 bg-zinc-950 text-zinc-100 space-y-4">
           <h2 class="font-semibold text-lg">Controls</h2>
           <p class="text-sm opacity-70">Leptos + WebGPU + Tailwind</p>
+          {move || restore_prompt.get().then(|| view! {
+            <div class="rounded border border-zinc-700 bg-zinc-900 p-3 space-y-2 text-sm">
+              <p>"Restore the previous session?"</p>
+              <div class="flex gap-2">
+                <button
+                  class="px-3 py-1 rounded bg-zinc-700 hover:bg-zinc-600 transition-colors"
+                  on:click=move |_| {
+                    if let Some(file) = workspaces::autosaved() {
+                      app.load_file(file);
+                    }
+                    restore_prompt.set(false);
+                  }
+                >
+                  "Restore"
+                </button>
+                <button
+                  class="px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| restore_prompt.set(false)
+                >
+                  "Start fresh"
+                </button>
+              </div>
+            </div>
+          })}
 
           <div class="space-y-6">
+            <section class="space-y-2">
+              <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">Scene</h3>
+              <div class="flex gap-2">
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors disabled:opacity-40"
+                  title="Ctrl+Z"
+                  disabled=move || !app.history.with(|h| h.can_undo())
+                  on:click=move |_| history::undo(&app)
+                >
+                  "Undo"
+                </button>
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors disabled:opacity-40"
+                  title="Ctrl+Shift+Z"
+                  disabled=move || !app.history.with(|h| h.can_redo())
+                  on:click=move |_| history::redo(&app)
+                >
+                  "Redo"
+                </button>
+              </div>
+              <div class="flex flex-wrap gap-2">
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| scene_error.set(scene_io::save(&app, Syntax::Json).err().map(|e| format!("save failed: {e}")))
+                >
+                  "Save (.json)"
+                </button>
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| scene_error.set(scene_io::save(&app, Syntax::Ron).err().map(|e| format!("save failed: {e}")))
+                >
+                  "Save (.ron)"
+                </button>
+                <label class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors cursor-pointer">
+                  "Load…"
+                  <input type="file" accept=".json,.ron" class="hidden"
+                    on:change=move |ev| {
+                      let Some(target) = ev.target() else { return };
+                      let input: HtmlInputElement = target.unchecked_into();
+                      let Some(file) = input.files().and_then(|files| files.get(0)) else { return };
+                      // let the same file be picked again
+                      input.set_value("");
+                      wasm_bindgen_futures::spawn_local(async move {
+                        scene_error.set(scene_io::load(app, file).await.err().map(|e| format!("load failed: {e}")));
+                      });
+                    }/>
+                </label>
+              </div>
+              {move || scene_error.get().map(|msg| view! {
+                <p class="text-xs text-red-400">{msg}</p>
+              })}
+
+              <h4 class="text-sm font-semibold pt-2">"Workspaces (this browser)"</h4>
+              <div class="flex gap-2">
+                <input type="text" placeholder="name" class="flex-1 min-w-0 text-sm bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  bind:value=workspace_name/>
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| workspace_result(workspaces::save(&app, &workspace_name.get_untracked()))
+                >
+                  "Save"
+                </button>
+              </div>
+              <ul class="space-y-1">
+                {move || workspace_names.get().into_iter().map(|name| {
+                  let (title, open_name, delete_name) = (name.clone(), name.clone(), name.clone());
+                  view! {
+                    <li class="flex items-center gap-2 text-sm">
+                      <span class="flex-1 truncate" title=title>{name}</span>
+                      <button
+                        class="px-2 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                        on:click=move |_| workspace_result(workspaces::open(&app, &open_name))
+                      >
+                        "Open"
+                      </button>
+                      <button
+                        class="px-2 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                        on:click=move |_| workspace_result(workspaces::delete(&delete_name))
+                      >
+                        "Delete"
+                      </button>
+                    </li>
+                  }
+                }).collect_view()}
+              </ul>
+              {move || workspace_error.get().map(|msg| view! {
+                <p class="text-xs text-red-400">{msg}</p>
+              })}
+            </section>
+
+            <section class="space-y-2">
+              <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">Presets</h3>
+              <select class="w-full text-sm bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                on:change=move |ev| {
+                  if let Some(target) = ev.target() {
+                    let input: HtmlSelectElement = target.unchecked_into();
+                    if let Some(p) = Preset::from_id(&input.value()) {
+                      preset.set(p);
+                      preset_params.set(p.defaults());
+                    }
+                  }
+                }>
+                {Preset::ALL.iter().map(|&p| view! {
+                  <option value=p.id() selected=move || preset.get() == p>{p.name()}</option>
+                }).collect_view()}
+              </select>
+              <div class="flex items-center gap-2 text-sm">
+                <label class="flex items-center gap-1" class:hidden=move || !preset.get().uses_n()>
+                  "N"
+                  <input type="number" min="1" max="64" class="w-16 bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                    prop:value=move || preset_params.get().n.to_string()
+                    on:change=move |ev| {
+                      if let Some(target) = ev.target() {
+                        let input: HtmlInputElement = target.unchecked_into();
+                        if let Ok(v) = input.value().parse::<usize>() {
+                          preset_params.update(|p| p.n = v.clamp(1, 64));
+                        }
+                      }
+                    }/>
+                </label>
+                <label class="flex items-center gap-1">
+                  "spacing"
+                  <input type="number" min="0.05" max="5" step="0.05" class="w-20 bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                    prop:value=move || preset_params.get().spacing.to_string()
+                    on:change=move |ev| {
+                      if let Some(target) = ev.target() {
+                        let input: HtmlInputElement = target.unchecked_into();
+                        if let Ok(v) = input.value().parse::<f32>() {
+                          preset_params.update(|p| p.spacing = v);
+                        }
+                      }
+                    }/>
+                </label>
+                <label class="flex items-center gap-1">
+                  "q"
+                  <input type="number" step="0.5" class="w-16 bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                    prop:value=move || preset_params.get().q.to_string()
+                    on:change=move |ev| {
+                      if let Some(target) = ev.target() {
+                        let input: HtmlInputElement = target.unchecked_into();
+                        if let Ok(v) = input.value().parse::<f32>() {
+                          preset_params.update(|p| p.q = v);
+                        }
+                      }
+                    }/>
+                </label>
+              </div>
+              <button
+                class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                on:click=move |_| {
+                  let p = preset.get_untracked();
+                  app.load_preset(p.build(&preset_params.get_untracked()));
+                }
+              >
+                "Load preset"
+              </button>
+            </section>
+
+            <section class="space-y-2">
+              <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">Charges</h3>
+              <p class="text-xs opacity-60">
+                "Click or drag a box to select, Ctrl+click to toggle, Shift+click to add a charge; Delete removes the selection. Drag the gizmo's arrows or squares to move along an axis or in a plane."
+              </p>
+              <div class="flex gap-2">
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| app.add_charge(Charge3D {
+                    pos: app.orbit_rt.get_untracked().target,
+                    q: 1.0,
+                    vel: Vec3::ZERO,
+                  })
+                >
+                  "Add charge"
+                </button>
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors disabled:opacity-40"
+                  disabled=move || app.selection.with(Vec::is_empty)
+                  on:click=move |_| app.delete_selection()
+                >
+                  "Delete selected"
+                </button>
+              </div>
+              <div class="flex items-center gap-2 text-sm">
+                "Snap drags to grid"
+                <select class="bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  on:change=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlSelectElement = target.unchecked_into();
+                      app.snap.set(input.value().parse().ok());
+                    }
+                  }>
+                  <option value="off" selected=move || app.snap.get().is_none()>"Off"</option>
+                  {gizmo::SNAP_STEPS.iter().map(|&step| view! {
+                    <option value=step.to_string() selected=move || app.snap.get() == Some(step)>
+                      {step.to_string()}
+                    </option>
+                  }).collect_view()}
+                </select>
+              </div>
+              <SelectionTools app=app/>
+              <ChargeInspector app=app/>
+            </section>
+
             <section class="space-y-2">
               <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">Layers</h3>
               <label class="flex items-center gap-2 text-sm">
@@ -45,15 +300,24 @@ fn FieldCanvas() -> impl IntoView {
                   on:input=move |ev| {
                     if let Some(target) = ev.target() {
                       let input: HtmlInputElement = target.unchecked_into();
-                      app.show_e.set(input.checked());
+                      history::change_settings(&app, "show_e", || app.show_e.set(input.checked()));
                     }
                   }/>
                 "Show Electric (E)"
               </label>
-              <label class="flex items-center gap-2 text-sm opacity-40">
-                <input type="checkbox" disabled=true />
-                "Show Magnetic (B) — coming soon"
+              <label class="flex items-center gap-2 text-sm">
+                <input type="checkbox"
+                  prop:checked=move || app.show_b.get()
+                  on:input=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      history::change_settings(&app, "show_b", || app.show_b.set(input.checked()));
+                    }
+                  }/>
+                "Show Magnetic (B)"
               </label>
+              <ColormapSelect app=app kind=LineKind::E/>
+              <ColormapSelect app=app kind=LineKind::B/>
             </section>
 
             <section class="space-y-2">
@@ -63,18 +327,98 @@ fn FieldCanvas() -> impl IntoView {
                 <span class="font-mono">{move || app.seeds_per_charge_e.get()}</span>
               </label>
               <input type="range" min="4" max="500" step="1" class="w-full"
-                bind:value=app.seeds_per_charge_e
-                // prop:value=move || app.seeds_per_charge_e.get().to_string()
-                // slider handler
-                // on:input=move |ev| {
-                //     if let Some(target) = ev.target() {
-                //         let input: HtmlInputElement = target.unchecked_into();
-                //         if let Ok(v) = input.value().parse() {
-                //             app.seeds_per_charge_e.set(v);
-                //         }
-                //     }
-                // }
-                />
+                prop:value=move || app.seeds_per_charge_e.get()
+                on:input=move |ev| {
+                  if let Some(target) = ev.target() {
+                    let input: HtmlInputElement = target.unchecked_into();
+                    history::change_settings(&app, "seeds", || app.seeds_per_charge_e.set(input.value()));
+                  }
+                }/>
+              {move || app.compute_error.get().map(|msg| view! {
+                <p class="text-xs text-red-400">{msg}</p>
+              })}
+            </section>
+
+            <section class="space-y-2">
+              <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">Field evaluation</h3>
+              <select class="w-full text-sm bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                on:change=move |ev| {
+                  if let Some(target) = ev.target() {
+                    let input: HtmlSelectElement = target.unchecked_into();
+                    let eval = match input.value().as_str() {
+                      "tiled" => FieldEval::Tiled,
+                      "bh" => FieldEval::BarnesHut { theta: FieldEval::DEFAULT_THETA },
+                      _ => FieldEval::Direct,
+                    };
+                    history::change_settings(&app, "field_eval", || app.field_eval.set(eval));
+                  }
+                }>
+                <option value="direct" selected=move || app.field_eval.get() == FieldEval::Direct>
+                  "Direct sum"
+                </option>
+                <option value="tiled" selected=move || app.field_eval.get() == FieldEval::Tiled>
+                  "Tiled (shared memory)"
+                </option>
+                <option value="bh" selected=move || matches!(app.field_eval.get(), FieldEval::BarnesHut { .. })>
+                  "Barnes–Hut (approximate)"
+                </option>
+              </select>
+              {move || match app.field_eval.get() {
+                FieldEval::BarnesHut { theta } => Some(view! {
+                  <label class="text-sm block">
+                    "Opening angle θ: "
+                    <span class="font-mono">{format!("{:.2}", theta)}</span>
+                  </label>
+                  <input type="range" min="0.05" max="1.5" step="0.05" class="w-full"
+                    prop:value=theta.to_string()
+                    on:input=move |ev| {
+                      if let Some(target) = ev.target() {
+                        let input: HtmlInputElement = target.unchecked_into();
+                        if let Ok(v) = input.value().parse::<f32>() {
+                          history::change_settings(&app, "theta", || app.field_eval.set(FieldEval::BarnesHut { theta: v }));
+                        }
+                      }
+                    }/>
+                }),
+                _ => None,
+              }}
+              <label class="flex items-center gap-2 text-sm">
+                <input type="checkbox"
+                  prop:checked=move || matches!(app.field_source.get(), FieldSource::Baked(_))
+                  on:input=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      let source = if input.checked() {
+                        FieldSource::Baked(GridSettings::default())
+                      } else {
+                        FieldSource::Exact
+                      };
+                      history::change_settings(&app, "field_source", || app.field_source.set(source));
+                    }
+                  }/>
+                "Bake E into a 3D grid (trilinear)"
+              </label>
+              {move || match app.field_source.get() {
+                FieldSource::Baked(grid) => Some(view! {
+                  <div class="flex items-center gap-2 text-sm">
+                    "Grid resolution"
+                    <select class="bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                      on:change=move |ev| {
+                        if let Some(target) = ev.target() {
+                          let input: HtmlSelectElement = target.unchecked_into();
+                          if let Ok(res) = input.value().parse() {
+                            history::change_settings(&app, "grid_res", || app.field_source.set(FieldSource::Baked(GridSettings { res })));
+                          }
+                        }
+                      }>
+                      {GridSettings::RESOLUTIONS.iter().map(|&r| view! {
+                        <option value=r.to_string() selected=r == grid.res>{format!("{r}³")}</option>
+                      }).collect_view()}
+                    </select>
+                  </div>
+                }),
+                FieldSource::Exact => None,
+              }}
             </section>
 
             <section class="space-y-2">
@@ -89,12 +433,256 @@ fn FieldCanvas() -> impl IntoView {
                   if let Some(target) = ev.target() {
                     let input: HtmlInputElement = target.unchecked_into();
                     if let Ok(v) = input.value().parse::<f32>() {
-                      app.point_size_px.set(v);
+                      history::change_settings(&app, "point_size", || app.point_size_px.set(v));
+                    }
+                  }
+                }/>
+              <label class="text-sm block">
+                "GPU pool budget (MiB): "
+                <span class="font-mono">{move || format!("{:.0}", app.pool_budget_mib.get())}</span>
+              </label>
+              <input type="range" min="16" max="2048" step="16" class="w-full"
+                prop:value=move || app.pool_budget_mib.get().to_string()
+                on:input=move |ev| {
+                  if let Some(target) = ev.target() {
+                    let input: HtmlInputElement = target.unchecked_into();
+                    if let Ok(v) = input.value().parse::<f32>() {
+                      app.pool_budget_mib.set(v);
                     }
                   }
                 }/>
             </section>
 
+            <section class="space-y-2">
+              <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">Animation</h3>
+              <label class="flex items-center gap-2 text-sm">
+                <input type="checkbox"
+                  prop:checked=move || app.motion.get().move_charges
+                  on:input=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      history::change_settings(&app, "move_charges", || app.motion.update(|m| m.move_charges = input.checked()));
+                    }
+                  }/>
+                "Move charges along their velocity"
+              </label>
+              <label class="text-sm block">
+                "Auto-rotate (°/s): "
+                <span class="font-mono">{move || format!("{:.0}", app.motion.get().orbit_deg_per_s)}</span>
+              </label>
+              <input type="range" min="-90" max="90" step="1" class="w-full"
+                prop:value=move || app.motion.get().orbit_deg_per_s.to_string()
+                on:input=move |ev| {
+                  if let Some(target) = ev.target() {
+                    let input: HtmlInputElement = target.unchecked_into();
+                    if let Ok(v) = input.value().parse::<f32>() {
+                      history::change_settings(&app, "orbit_speed", || app.motion.update(|m| m.orbit_deg_per_s = v));
+                    }
+                  }
+                }/>
+            </section>
+
+            <section class="space-y-2">
+              <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">Export</h3>
+              <div class="flex flex-wrap gap-2">
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| run_export(export::streamlines_vtk)
+                >
+                  "Streamlines (.vtk)"
+                </button>
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| run_export(export::field_grid_vtk)
+                >
+                  {format!("Field grid {}³ (.vtk)", export::GRID_RES)}
+                </button>
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| run_export(export::scene_glb)
+                >
+                  "Download glTF (.glb)"
+                </button>
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| run_export(export::view_svg)
+                >
+                  "Current view (.svg)"
+                </button>
+              </div>
+              {move || export_error.get().map(|msg| view! {
+                <p class="text-xs text-red-400">{msg}</p>
+              })}
+
+              <h4 class="text-sm font-semibold pt-2">"PNG snapshot (WebGPU)"</h4>
+              <div class="flex items-center gap-2 text-sm">
+                <input type="number" min="16" max="16384" class="w-24 bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  prop:value=move || app.capture_opts.get().width.to_string()
+                  on:change=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      if let Ok(v) = input.value().parse::<u32>() {
+                        app.capture_opts.update(|o| o.width = v.max(1));
+                      }
+                    }
+                  }/>
+                "×"
+                <input type="number" min="16" max="16384" class="w-24 bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  prop:value=move || app.capture_opts.get().height.to_string()
+                  on:change=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      if let Ok(v) = input.value().parse::<u32>() {
+                        app.capture_opts.update(|o| o.height = v.max(1));
+                      }
+                    }
+                  }/>
+                "px"
+              </div>
+              <div class="flex items-center gap-2 text-sm">
+                "Supersample"
+                <select class="bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  on:change=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlSelectElement = target.unchecked_into();
+                      if let Ok(v) = input.value().parse() {
+                        app.capture_opts.update(|o| o.supersample = v);
+                      }
+                    }
+                  }>
+                  {CaptureOptions::SUPERSAMPLES.iter().map(|&n| view! {
+                    <option value=n.to_string() selected=move || app.capture_opts.get().supersample == n>
+                      {format!("{n}×")}
+                    </option>
+                  }).collect_view()}
+                </select>
+              </div>
+              <label class="flex items-center gap-2 text-sm">
+                <input type="checkbox"
+                  prop:checked=move || app.capture_opts.get().msaa != 1
+                  on:input=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      let msaa = if input.checked() { CAPTURE_MSAA } else { 1 };
+                      app.capture_opts.update(|o| o.msaa = msaa);
+                    }
+                  }/>
+                {format!("{CAPTURE_MSAA}× MSAA")}
+              </label>
+              <label class="flex items-center gap-2 text-sm">
+                <input type="checkbox"
+                  prop:checked=move || app.capture_opts.get().transparent
+                  on:input=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      app.capture_opts.update(|o| o.transparent = input.checked());
+                    }
+                  }/>
+                "Transparent background"
+              </label>
+              <button
+                class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                on:click=move |_| app.capture_request.set(Some(app.capture_opts.get_untracked()))
+              >
+                "Save PNG"
+              </button>
+              {move || app.capture_status.get().map(|msg| view! {
+                <p class="text-xs opacity-70">{msg}</p>
+              })}
+
+              <h4 class="text-sm font-semibold pt-2">"Frame sequence"</h4>
+              <div class="flex items-center gap-2 text-sm">
+                <input type="number" min="16" max="8192" class="w-24 bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  prop:value=move || app.record_opts.get().frame.width.to_string()
+                  on:change=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      if let Ok(v) = input.value().parse::<u32>() {
+                        app.record_opts.update(|o| o.frame.width = v.max(1));
+                      }
+                    }
+                  }/>
+                "×"
+                <input type="number" min="16" max="8192" class="w-24 bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  prop:value=move || app.record_opts.get().frame.height.to_string()
+                  on:change=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      if let Ok(v) = input.value().parse::<u32>() {
+                        app.record_opts.update(|o| o.frame.height = v.max(1));
+                      }
+                    }
+                  }/>
+                "px"
+              </div>
+              <div class="flex items-center gap-2 text-sm">
+                <select class="bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  on:change=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlSelectElement = target.unchecked_into();
+                      if let Ok(v) = input.value().parse() {
+                        app.record_opts.update(|o| o.fps = v);
+                      }
+                    }
+                  }>
+                  {RecordOptions::FPS.iter().map(|&n| view! {
+                    <option value=n.to_string() selected=move || app.record_opts.get().fps == n>
+                      {format!("{n} fps")}
+                    </option>
+                  }).collect_view()}
+                </select>
+                <input type="number" min="0.1" max="600" step="0.5" class="w-20 bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                  prop:value=move || app.record_opts.get().seconds.to_string()
+                  on:change=move |ev| {
+                    if let Some(target) = ev.target() {
+                      let input: HtmlInputElement = target.unchecked_into();
+                      if let Ok(v) = input.value().parse::<f32>() {
+                        app.record_opts.update(|o| o.seconds = v.max(0.0));
+                      }
+                    }
+                  }/>
+                "s"
+              </div>
+              <select class="text-sm bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+                on:change=move |ev| {
+                  if let Some(target) = ev.target() {
+                    let input: HtmlSelectElement = target.unchecked_into();
+                    let format = match input.value().as_str() {
+                      "webm" => RecordFormat::WebM,
+                      _ => RecordFormat::PngZip,
+                    };
+                    app.record_opts.update(|o| o.format = format);
+                  }
+                }>
+                <option value="zip" selected=move || app.record_opts.get().format == RecordFormat::PngZip>
+                  "PNG frames (.zip, WebGPU)"
+                </option>
+                <option value="webm" selected=move || app.record_opts.get().format == RecordFormat::WebM>
+                  "WebM video"
+                </option>
+              </select>
+              <div class="flex flex-wrap gap-2">
+                <button
+                  class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors disabled:opacity-50"
+                  disabled=move || app.recording.get()
+                  on:click=move |_| app.record_request.set(Some(app.record_opts.get_untracked()))
+                >
+                  {move || format!("Record {} frames", app.record_opts.get().frame_count())}
+                </button>
+                {move || app.recording.get().then(|| view! {
+                  <button
+                    class="text-sm px-3 py-1 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                    on:click=move |_| app.recording.set(false)
+                  >
+                    "Cancel"
+                  </button>
+                })}
+              </div>
+              {move || app.record_status.get().map(|msg| view! {
+                <p class="text-xs opacity-70">{msg}</p>
+              })}
+            </section>
+
             <section class="space-y-2">
               <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">
                 Playback
@@ -111,6 +699,30 @@ fn FieldCanvas() -> impl IntoView {
                 {move || if app.paused.get() { "Paused" } else { "Running" }}
               </p>
             </section>
+
+            <section class="space-y-2">
+              <div class="flex items-center justify-between">
+                <h3 class="font-semibold text-sm uppercase tracking-wide opacity-70">GPU errors</h3>
+                <button
+                  class="text-xs px-2 py-0.5 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors"
+                  on:click=move |_| app.gpu_errors.set(vec![])
+                >
+                  "Clear"
+                </button>
+              </div>
+              {move || {
+                let log = app.gpu_errors.get();
+                if log.is_empty() {
+                  view! { <p class="text-xs opacity-60">"None"</p> }.into_any()
+                } else {
+                  view! {
+                    <ul class="text-xs font-mono text-red-400 space-y-1 max-h-48 overflow-y-auto break-words">
+                      {log.into_iter().map(|msg| view! { <li>{msg}</li> }).collect_view()}
+                    </ul>
+                  }.into_any()
+                }
+              }}
+            </section>
           </div>
         </aside>
 
@@ -124,6 +736,161 @@ fn FieldCanvas() -> impl IntoView {
     }
 }
 
+/// Transforms of the selected group, about its centroid.
+#[component]
+fn SelectionTools(app: AppState) -> impl IntoView {
+    let rotate_deg = RwSignal::new("90".to_string());
+    let scale_by = RwSignal::new("1.5".to_string());
+    let axes = [("X", Vec3::X), ("Y", Vec3::Y), ("Z", Vec3::Z)];
+    let button = "px-2 py-0.5 rounded bg-zinc-800 hover:bg-zinc-700 transition-colors";
+    let number = "w-16 bg-zinc-900 border border-zinc-700 rounded px-1 font-mono";
+
+    move || {
+        let n = app.selection.with(Vec::len);
+        (n > 0).then(|| view! {
+          <div class="rounded border border-zinc-800 p-2 space-y-2 text-xs">
+            <p class="opacity-70">{format!("{n} selected")}</p>
+            <div class="flex items-center gap-1">
+              "Rotate"
+              <input type="number" step="15" class=number bind:value=rotate_deg/>
+              "° about"
+              {axes.map(|(name, axis)| view! {
+                <button class=button
+                  on:click=move |_| {
+                    if let Ok(deg) = rotate_deg.get_untracked().parse::<f32>() {
+                      app.transform_selection(GroupOp::Rotate { axis, angle: deg.to_radians() });
+                    }
+                  }
+                >
+                  {name}
+                </button>
+              }).collect_view()}
+            </div>
+            <div class="flex items-center gap-1">
+              "Scale spacing ×"
+              <input type="number" step="0.1" min="0" class=number bind:value=scale_by/>
+              <button class=button
+                on:click=move |_| {
+                  if let Ok(s) = scale_by.get_untracked().parse::<f32>() {
+                    app.transform_selection(GroupOp::Scale(s));
+                  }
+                }
+              >
+                "Apply"
+              </button>
+            </div>
+            <div class="flex items-center gap-1">
+              "Mirror across"
+              {axes.map(|(name, axis)| view! {
+                <button class=button title=format!("plane normal to {name}")
+                  on:click=move |_| app.transform_selection(GroupOp::Mirror(axis))
+                >
+                  {name}
+                </button>
+              }).collect_view()}
+              <button class=format!("{button} ml-auto") on:click=move |_| app.transform_selection(GroupOp::Negate)>
+                "Negate q"
+              </button>
+            </div>
+          </div>
+        })
+    }
+}
+
+/// Palette of the `kind` lines, for the renderers and the exports.
+#[component]
+fn ColormapSelect(app: AppState, kind: LineKind) -> impl IntoView {
+    view! {
+      <div class="flex items-center gap-2 text-sm">
+        {format!("{} colormap", kind.name())}
+        <select class="bg-zinc-900 border border-zinc-700 rounded px-2 py-1"
+          on:change=move |ev| {
+            if let Some(target) = ev.target() {
+              let input: HtmlSelectElement = target.unchecked_into();
+              if let Some(map) = Colormap::from_id(&input.value()) {
+                history::change_settings(&app, "colormaps", || app.colormaps.update(|c| match kind {
+                  LineKind::E => c.e = map,
+                  LineKind::B => c.b = map,
+                }));
+              }
+            }
+          }>
+          {Colormap::ALL.map(|map| view! {
+            <option value=map.id() selected=move || app.colormaps.get().of(kind) == map>
+              {map.name()}
+            </option>
+          }).collect_view()}
+        </select>
+      </div>
+    }
+}
+
+/// One row per charge with editable position, charge and velocity; a row
+/// selects its charge. Rows are rebuilt only when the count changes, so drags
+/// just update the fields.
+#[component]
+fn ChargeInspector(app: AppState) -> impl IntoView {
+    let count = Memo::new(move |_| app.charges.with(Vec::len));
+    let field = move |i: usize,
+                      label: &'static str,
+                      get: fn(&Charge3D) -> f32,
+                      set: fn(&mut Charge3D, f32)| {
+        view! {
+          <label class="flex items-center gap-1">
+            <span class="opacity-60">{label}</span>
+            <input type="number" step="0.05"
+              class="w-full min-w-0 bg-zinc-900 border border-zinc-700 rounded px-1 font-mono"
+              prop:value=move || app.charges.with(|cs| cs.get(i).map(|c| format!("{:.3}", get(c))).unwrap_or_default())
+              on:change=move |ev| {
+                let Some(target) = ev.target() else { return };
+                let input: HtmlInputElement = target.unchecked_into();
+                let Ok(v) = input.value().parse::<f32>() else { return };
+                if let Some(mut c) = app.charges.with_untracked(|cs| cs.get(i).copied()) {
+                  set(&mut c, v);
+                  app.set_charge(i, c);
+                }
+              }/>
+          </label>
+        }
+    };
+
+    view! {
+      <div class="max-h-72 overflow-y-auto space-y-1 pr-1">
+        {move || (0..count.get()).map(|i| view! {
+          <div
+            class="rounded border p-1 text-xs space-y-1"
+            class=("border-amber-400", move || app.selection.with(|sel| sel.contains(&i)))
+            class=("border-zinc-800", move || !app.selection.with(|sel| sel.contains(&i)))
+            on:click=move |ev| app.select(i, ev.ctrl_key() || ev.meta_key())
+          >
+            <div class="flex items-center justify-between">
+              <span class="font-mono">{format!("#{}", i + 1)}</span>
+              <button
+                class="px-1 opacity-60 hover:opacity-100"
+                title="Delete"
+                on:click=move |ev| {
+                  ev.stop_propagation();
+                  app.delete_charge(i);
+                }
+              >
+                "×"
+              </button>
+            </div>
+            <div class="grid grid-cols-4 gap-1">
+              {field(i, "x", |c| c.pos.x, |c, v| c.pos.x = v)}
+              {field(i, "y", |c| c.pos.y, |c, v| c.pos.y = v)}
+              {field(i, "z", |c| c.pos.z, |c, v| c.pos.z = v)}
+              {field(i, "q", |c| c.q, |c, v| c.q = v)}
+              {field(i, "vx", |c| c.vel.x, |c, v| c.vel.x = v)}
+              {field(i, "vy", |c| c.vel.y, |c, v| c.vel.y = v)}
+              {field(i, "vz", |c| c.vel.z, |c, v| c.vel.z = v)}
+            </div>
+          </div>
+        }).collect_view()}
+      </div>
+    }
+}
+
 #[component]
 fn CanvasWG(class: &'static str, app: AppState) -> impl IntoView {
     let canvas_ref: NodeRef<leptos::html::Canvas> = NodeRef::new();
@@ -141,6 +908,19 @@ fn CanvasWG(class: &'static str, app: AppState) -> impl IntoView {
     view! {
       <div class="absolute inset-0">
         <canvas node_ref=canvas_ref class=class></canvas>
+        <gizmo::GizmoOverlay app=app/>
+        {move || app.marquee.get().map(|m| {
+          let (left, top, width, height) = m.rect();
+          view! {
+            <div
+              class="absolute border border-amber-400 bg-amber-400/10 pointer-events-none"
+              style:left=format!("{left}px")
+              style:top=format!("{top}px")
+              style:width=format!("{width}px")
+              style:height=format!("{height}px")
+            ></div>
+          }
+        })}
       </div>
     }
 }
@@ -149,7 +929,7 @@ pub async fn create_renderer_and_kick_off_raf_loop(
     canvas: web_sys::HtmlCanvasElement,
     app: AppState,
 ) -> () {
-    let renderer_sig: RwSignal<Option<WgpuRenderer>, leptos::prelude::LocalStorage> =
+    let renderer_sig: RwSignal<Option<Renderer>, leptos::prelude::LocalStorage> =
         RwSignal::new_local(None);
 
     // --- canvas sizing (DPR)
@@ -168,14 +948,13 @@ pub async fn create_renderer_and_kick_off_raf_loop(
     };
 
     let canvas_for_loop = canvas.clone();
+    let errors = ErrorSink::default();
 
-    let mut ren = WgpuRenderer::new(
-        canvas,
-        &app.charges.get_untracked(),
-        app.point_size_px.get_untracked(),
-    )
-    .await
-    .expect("wgpu init");
+    let Some(mut ren) = build_renderer(canvas, app, errors.clone()).await else {
+        app.hud_text
+            .set("renderer init failed (see GPU errors)".into());
+        return;
+    };
 
     ren.update_viewproj(cam.view().to_cols_array(), cam.proj().to_cols_array());
     renderer_sig.set(Some(ren));
@@ -186,20 +965,24 @@ pub async fn create_renderer_and_kick_off_raf_loop(
         // let n_seeds = app.seeds_per_charge_e.get();
         let px = app.point_size_px.get();
         let charges = app.charges.get(); // positions and q
+        let selection = app.selection.get();
         // optional: cheaper params while dragging
         // let dragging = app.drag.get().active;
 
-        // always update point size (cheap)
+        let budget_mib = app.pool_budget_mib.get();
+
+        // always update point size and pool budget (cheap)
         renderer_sig.update(|opt| {
             if let Some(r) = opt.as_mut() {
                 r.set_point_size(px);
+                r.set_pool_budget((budget_mib as f64 * 1024.0 * 1024.0) as u64);
             }
         });
 
-        // upload charge centers every time charges change
+        // upload charge centers every time charges or the selection change
         renderer_sig.update(|opt| {
             if let Some(r) = opt.as_mut() {
-                r.update_charges(&charges);
+                r.update_charges(&charges, &selection);
             }
         });
 
@@ -225,7 +1008,7 @@ pub async fn create_renderer_and_kick_off_raf_loop(
         //     let shell_r = 0.06f32;
         //     for c in &charges {
         //         let sign = if c.q >= 0.0 { 1.0 } else { -1.0 };
-        //         for s0 in crate::seed::fibonacci_sphere(c.pos, shell_r, n_seeds) {
+        //         for s0 in em_viz_core::seed::fibonacci_sphere(c.pos, shell_r, n_seeds) {
         //             seeds.push([s0.x, s0.y, s0.z, sign]);
         //         }
         //     }
@@ -242,11 +1025,46 @@ pub async fn create_renderer_and_kick_off_raf_loop(
         // let _render_timer = Scope::new("rendered kickoff");
         // renderer_sig.update(|opt| {
         //     if let Some(r) = opt.as_mut() {
-        //         r.start_compute_ribbons_e(&charges4, &seeds, h_step, max_pts);
+        //         r.start_compute_ribbons_e(&charges, &seeds, h_step, max_pts, eval, source);
         //     }
         // });
     });
 
+    Effect::new(move |_| {
+        let colormaps = app.colormaps.get();
+        renderer_sig.update(|opt| {
+            if let Some(r) = opt.as_mut() {
+                r.set_colormaps(colormaps);
+            }
+        });
+    });
+
+    // B lines are traced on the CPU, again whenever the charges or the
+    // seeding change (live while a velocity handle is dragged). Without a
+    // moving charge there are none, so only the first such change clears them.
+    // A recording traces its own frames; this catches up once it ends.
+    Effect::new(move |shown: Option<bool>| {
+        if app.recording.get() {
+            return true;
+        }
+        let moving = app
+            .charges
+            .with(|cs| cs.iter().any(|c| c.vel != Vec3::ZERO));
+        let show = app.show_b.get() && moving;
+        app.seeding.track();
+        app.integrator.track();
+        if !show && shown == Some(false) {
+            return false;
+        }
+        let scene = app.scene();
+        renderer_sig.update(|opt| {
+            if let Some(r) = opt.as_mut() {
+                r.set_b_lines(show.then_some(&scene));
+            }
+        });
+        show
+    });
+
     // RAF: drive camera + render
     let raf = std::rc::Rc::new(std::cell::RefCell::new(None::<Closure<dyn FnMut(f64)>>));
     let raf2 = raf.clone();
@@ -264,6 +1082,60 @@ pub async fn create_renderer_and_kick_off_raf_loop(
         win.request_animation_frame(raf.borrow().as_ref().unwrap().as_ref().unchecked_ref())
             .unwrap();
 
+        // Surface GPU errors; after a device loss drop the renderer and build a
+        // new one from the (untouched) app state.
+        let new_errors = errors.drain();
+        if !new_errors.is_empty() {
+            app.gpu_errors.update(|log| {
+                log.extend(new_errors);
+                let excess = log.len().saturating_sub(MAX_GPU_ERRORS);
+                log.drain(..excess);
+            });
+        }
+        if errors.take_lost().is_some() {
+            renderer_sig.set(None);
+            let (canvas, errors) = (canvas_for_loop.clone(), errors.clone());
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(r) = build_renderer(canvas, app, errors).await {
+                    renderer_sig.set(Some(r));
+                }
+            });
+        }
+
+        if let Some(orbit) = app.orbit_request.get_untracked() {
+            app.orbit_request.set(None);
+            orbit_ctl.borrow_mut().set_orbit(orbit);
+        }
+
+        // Captures run even while paused, from the last frame's camera.
+        if let Some(opts) = app.capture_request.get_untracked() {
+            app.capture_request.set(None);
+            save_capture(renderer_sig, &cam, opts, app);
+        }
+
+        if let Some(opts) = app.record_request.get_untracked() {
+            app.record_request.set(None);
+            if !app.recording.get_untracked() {
+                app.recording.set(true);
+                wasm_bindgen_futures::spawn_local(record::record(
+                    renderer_sig,
+                    orbit_ctl.clone(),
+                    canvas_for_loop.clone(),
+                    app,
+                    opts,
+                ));
+            }
+        }
+        // The recorder drives compute and rendering itself, one fixed step per frame.
+        if app.recording.get_untracked() {
+            LAST_T_MS.with(|last| last.set(0.0));
+            app.hud_text.set(format!(
+                "recording {}",
+                app.record_status.get_untracked().unwrap_or_default()
+            ));
+            return;
+        }
+
         // If paused: show HUD "paused", reset timing state, and skip all heavy work
         if app.paused.get_untracked() {
             LAST_T_MS.with(|last| last.set(0.0));
@@ -272,6 +1144,8 @@ pub async fn create_renderer_and_kick_off_raf_loop(
             return;
         }
 
+        let prev_t_ms = LAST_T_MS.with(|last| last.get());
+
         // --- FPS/frametime + timing HUD update
         LAST_T_MS.with(|last| {
             let prev = last.get();
@@ -289,22 +1163,41 @@ pub async fn create_renderer_and_kick_off_raf_loop(
 
                     // Drain perf::Scope + GPU timings collected this frame
                     let timings_str = perf::drain_frame_timings();
+                    let (backend, timing) = renderer_sig.with_untracked(|opt| {
+                        opt.as_ref()
+                            .map(|r| (r.backend_name(), r.timing_source()))
+                            .unwrap_or(("rebuilding renderer", "-"))
+                    });
                     app.hud_text.set(format!(
-                        "{:.1} fps | {:.2} ms\n{}",
-                        fps, smoothed, timings_str
+                        "{} | timing: {}\n{:.1} fps | {:.2} ms\n{}",
+                        backend, timing, fps, smoothed, timings_str
                     ));
                 });
             }
             last.set(t_ms);
         });
 
+        // Live motion follows frame time (clamped after stalls); recordings step
+        // it by exactly 1 / fps instead.
+        let motion = app.motion.get_untracked();
+        if !motion.is_still() && prev_t_ms != 0.0 {
+            let dt = ((t_ms - prev_t_ms) / 1000.0).min(0.1) as f32;
+            if motion.move_charges {
+                app.charges.update(|c| motion.advance_charges(c, dt));
+            }
+            let mut ctl = orbit_ctl.borrow_mut();
+            let orbit = motion.advance_orbit(ctl.orbit(), dt);
+            ctl.set_orbit(orbit);
+        }
+
         let dpr = win.device_pixel_ratio();
         let cw = (canvas_for_loop.client_width() as f64 * dpr).round() as u32;
         let ch = (canvas_for_loop.client_height() as f64 * dpr).round() as u32;
         if ch > 0 {
             cam.aspect = (cw as f32) / (ch as f32);
         }
-        cam.update_from_orbit(&orbit_ctl.borrow().orbit());
+        let orbit = orbit_ctl.borrow().orbit();
+        cam.update_from_orbit(&orbit);
 
         // compute matrices once
         let view = cam.view();
@@ -314,35 +1207,48 @@ pub async fn create_renderer_and_kick_off_raf_loop(
         // realtime for picking
         app.inv_vp.set(inv_vp);
         app.eye_rt.set(cam.eye);
+        app.viewport_rt.set((cw.max(1), ch.max(1)));
+        // only on change: scene links are rewritten whenever this fires
+        if app.orbit_rt.get_untracked() != orbit {
+            app.orbit_rt.set(orbit);
+        }
 
         renderer_sig.update_untracked(|opt| {
             let _pre_render = Scope::new("raf pre-render");
             let charges = app.charges.get_untracked(); // positions and q
             let n_seeds = app.seeds_per_charge_e.get_untracked();
-            let charges4: Vec<[f32; 4]> = charges
-                .iter()
-                .map(|c| [c.pos.x, c.pos.y, c.pos.z, c.q])
-                .collect();
+            let eval = app.field_eval.get_untracked();
+            let source = app.field_source.get_untracked();
+            let (seeding, integrator) =
+                (app.seeding.get_untracked(), app.integrator.get_untracked());
             let n_seeds_num: usize = n_seeds.parse().expect("Failed to parse integer");
-            let mut seeds: Vec<[f32; 4]> = Vec::with_capacity(charges.len() * n_seeds_num);
-            {
+            let seeds = {
                 let timer_message = &format!("seeds.build n={}", charges.len() * n_seeds_num);
                 let _seed_timer = Scope::new(timer_message);
-                let shell_r = 0.06f32;
-                for c in &charges {
-                    let sign = if c.q >= 0.0 { 1.0 } else { -1.0 };
-                    for s0 in crate::seed::fibonacci_sphere(c.pos, shell_r, n_seeds_num) {
-                        seeds.push([s0.x, s0.y, s0.z, sign]);
-                    }
-                }
-            }
-            let (h_step, max_pts) = (0.015f32, 400u32);
+                em_viz_core::seed::e_seeds(&charges, n_seeds_num, seeding.shell_radius)
+            };
+            let integrator = Integrator { eval, ..integrator };
             drop(_pre_render);
             if let Some(r) = opt.as_mut() {
                 r.resize(cw, ch);
                 r.update_viewproj(view.to_cols_array(), proj.to_cols_array());
-                r.start_compute_ribbons_e(&charges4, &seeds, h_step, max_pts);
-                let _ = r.render();
+                match r.start_compute_ribbons_e(&charges, &seeds, &integrator, source) {
+                    Ok(()) => {
+                        if app.compute_error.get_untracked().is_some() {
+                            app.compute_error.set(None);
+                        }
+                    }
+                    Err(e) => {
+                        r.clear_ribbons();
+                        let msg = e.to_string();
+                        if app.compute_error.get_untracked().as_deref() != Some(msg.as_str()) {
+                            app.compute_error.set(Some(msg));
+                        }
+                    }
+                }
+                if let Err(e) = r.render() {
+                    errors.push("render", e);
+                }
             }
         });
     }) as Box<dyn FnMut(f64)>));
@@ -352,3 +1258,75 @@ pub async fn create_renderer_and_kick_off_raf_loop(
         .request_animation_frame(raf2.borrow().as_ref().unwrap().as_ref().unchecked_ref())
         .unwrap();
 }
+
+const MAX_GPU_ERRORS: usize = 50;
+
+/// Render `opts` offscreen and download it as a PNG once the readback lands.
+fn save_capture(
+    renderer_sig: RwSignal<Option<Renderer>, leptos::prelude::LocalStorage>,
+    cam: &camera::Camera,
+    opts: CaptureOptions,
+    app: AppState,
+) {
+    let mut cam = cam.clone();
+    cam.aspect = opts.width as f32 / opts.height.max(1) as f32;
+    let (view, proj) = (cam.view().to_cols_array(), cam.proj().to_cols_array());
+    let pending = renderer_sig.try_update_untracked(|opt| match opt.as_mut() {
+        Some(r) => r.capture(&opts, view, proj),
+        None => Err(anyhow::anyhow!("renderer is not ready")),
+    });
+    let pending = match pending {
+        Some(Ok(p)) => p,
+        Some(Err(e)) => return app.capture_status.set(Some(format!("capture failed: {e}"))),
+        None => return,
+    };
+    let (rw, rh) = opts.render_size();
+    app.capture_status
+        .set(Some(format!("rendering {rw}×{rh}…")));
+    wasm_bindgen_futures::spawn_local(async move {
+        let name = format!("em_viz_{}x{}.png", opts.width, opts.height);
+        let saved = async {
+            let img = pending.finish().await?;
+            let png = capture::encode_png(&img)?;
+            download::save_bytes(&name, "image/png", &png)
+        };
+        app.capture_status.set(Some(match saved.await {
+            Ok(()) => format!("saved {name}"),
+            Err(e) => format!("capture failed: {e}"),
+        }));
+    });
+}
+
+/// Create a renderer from the current app state. Runs at startup and again after
+/// a device loss, so everything it needs is read from `app`.
+async fn build_renderer(
+    canvas: web_sys::HtmlCanvasElement,
+    app: AppState,
+    errors: ErrorSink,
+) -> Option<Renderer> {
+    match Renderer::new(
+        canvas,
+        &app.charges.get_untracked(),
+        app.point_size_px.get_untracked(),
+        errors,
+    )
+    .await
+    {
+        Ok(mut r) => {
+            // the Effects that feed these only run on edits, so a renderer
+            // rebuilt after device loss gets the current state here
+            r.set_pool_budget(
+                (app.pool_budget_mib.get_untracked() as f64 * 1024.0 * 1024.0) as u64,
+            );
+            r.update_charges(&app.charges.get_untracked(), &app.selection.get_untracked());
+            r.set_colormaps(app.colormaps.get_untracked());
+            let scene = app.scene();
+            r.set_b_lines(app.show_b.get_untracked().then_some(&scene));
+            Some(r)
+        }
+        Err(e) => {
+            app.gpu_errors.update(|log| log.push(format!("[init] {e}")));
+            None
+        }
+    }
+}
@@ -1,55 +1,9 @@
-use glam::{Mat4, Vec3};
-use wasm_bindgen::JsCast;
-
-pub struct Camera {
-    pub eye: Vec3,
-    pub target: Vec3,
-    pub up: Vec3,
-    pub fov_y: f32,
-    pub aspect: f32,
-    pub near: f32,
-    pub far: f32,
-}
-impl Camera {
-    pub fn new(aspect: f32) -> Self {
-        Self {
-            eye: Vec3::new(0.0, 0.0, 0.0),
-            target: Vec3::ZERO,
-            up: Vec3::Y,
-            fov_y: 50f32.to_radians(),
-            aspect,
-            near: 0.01,
-            far: 250.0,
-        }
-    }
-    pub fn view(&self) -> Mat4 {
-        Mat4::look_at_rh(self.eye, self.target, self.up)
-    }
-    pub fn proj(&self) -> Mat4 {
-        Mat4::perspective_rh_gl(self.fov_y, self.aspect, self.near, self.far)
-    }
-    pub fn update_from_orbit(&mut self, o: &Orbit) {
-        self.target = o.target;
-        self.eye = orbit_eye(o.target, o.yaw, o.pitch, o.radius);
-    }
-}
+//! Orbit controls for the canvas; the camera math itself lives in `em_viz_core`.
 
-#[derive(Clone, Copy)]
-pub struct Orbit {
-    pub yaw: f32,
-    pub pitch: f32,
-    pub radius: f32,
-    pub target: Vec3,
-}
+use glam::Vec3;
+use wasm_bindgen::JsCast;
 
-pub fn orbit_eye(target: Vec3, yaw: f32, pitch: f32, radius: f32) -> Vec3 {
-    let cp = pitch.cos();
-    let sp = pitch.sin();
-    let cy = yaw.cos();
-    let sy = yaw.sin();
-    let dir = Vec3::new(cy * cp, sp, sy * cp);
-    target + radius * dir
-}
+pub use em_viz_core::camera::{Camera, Orbit};
 
 pub struct OrbitController {
     orbit: Orbit,
@@ -68,6 +22,9 @@ impl OrbitController {
     pub fn orbit(&self) -> Orbit {
         self.orbit
     }
+    pub fn set_orbit(&mut self, orbit: Orbit) {
+        self.orbit = orbit;
+    }
     // pub fn set_aspect(&mut self, cam: &mut Camera, aspect: f32) {
     //     cam.aspect = aspect;
     //     cam.update_from_orbit(&self.orbit);
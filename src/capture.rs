@@ -0,0 +1,88 @@
+//! High-resolution stills: the WebGPU renderer draws into an offscreen target
+//! and copies it to a buffer, and this module maps it back and encodes PNGs.
+
+use anyhow::{Context, Result, anyhow};
+use em_viz_core::image::RgbaImage;
+use futures_channel::oneshot;
+
+/// What the sidebar asks for. The image is rendered at `width * supersample` by
+/// `height * supersample`, reduced with a box filter, and then encoded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CaptureOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Multisample count of the offscreen target: 1 or `CAPTURE_MSAA`.
+    pub msaa: u32,
+    /// 1, 2 or 4.
+    pub supersample: u32,
+    pub transparent: bool,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            width: 3840,
+            height: 2160,
+            msaa: crate::wgpu_renderer::CAPTURE_MSAA,
+            supersample: 2,
+            transparent: false,
+        }
+    }
+}
+
+impl CaptureOptions {
+    pub const SUPERSAMPLES: [u32; 3] = [1, 2, 4];
+
+    /// Size of the offscreen render target.
+    pub fn render_size(&self) -> (u32, u32) {
+        (
+            self.width * self.supersample,
+            self.height * self.supersample,
+        )
+    }
+}
+
+/// A capture submitted to the GPU whose readback hasn't been mapped yet. Owns
+/// everything it needs, so the renderer stays free while it resolves.
+pub struct PendingCapture {
+    pub(crate) buffer: wgpu::Buffer,
+    pub(crate) row_bytes: u32,
+    pub(crate) bgra: bool,
+    pub(crate) opts: CaptureOptions,
+}
+
+impl PendingCapture {
+    /// Wait for the readback and return the final straight-alpha image.
+    pub async fn finish(self) -> Result<RgbaImage> {
+        let (tx, rx) = oneshot::channel();
+        let slice = self.buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |r| {
+            let _ = tx.send(r);
+        });
+        rx.await
+            .context("readback dropped")?
+            .map_err(|e| anyhow!("mapping readback: {e}"))?;
+
+        let (w, h) = self.opts.render_size();
+        let img = {
+            let data = slice.get_mapped_range();
+            RgbaImage::from_padded_rows(&data, w, h, self.row_bytes as usize, self.bgra)
+        };
+        self.buffer.unmap();
+        let mut img = img.downsample(self.opts.supersample);
+        img.unpremultiply();
+        Ok(img)
+    }
+}
+
+pub fn encode_png(img: &RgbaImage) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut enc = png::Encoder::new(&mut out, img.width, img.height);
+    enc.set_color(png::ColorType::Rgba);
+    enc.set_depth(png::BitDepth::Eight);
+    enc.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = enc.write_header()?;
+    writer.write_image_data(&img.pixels)?;
+    writer.finish()?;
+    Ok(out)
+}
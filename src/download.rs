@@ -0,0 +1,39 @@
+//! Save generated files from the browser: wrap the bytes in a Blob and click a
+//! temporary `<a download>` pointing at an object URL.
+
+use anyhow::{Result, anyhow};
+use wasm_bindgen::JsCast;
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+pub fn save_text(filename: &str, mime: &str, text: &str) -> Result<()> {
+    let parts = js_sys::Array::of1(&text.into());
+    let opts = BlobPropertyBag::new();
+    opts.set_type(mime);
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &opts)
+        .map_err(|e| anyhow!("creating blob: {e:?}"))?;
+    save_blob(filename, &blob)
+}
+
+pub fn save_bytes(filename: &str, mime: &str, bytes: &[u8]) -> Result<()> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let opts = BlobPropertyBag::new();
+    opts.set_type(mime);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &opts)
+        .map_err(|e| anyhow!("creating blob: {e:?}"))?;
+    save_blob(filename, &blob)
+}
+
+pub fn save_blob(filename: &str, blob: &Blob) -> Result<()> {
+    let url = Url::create_object_url_with_blob(blob).map_err(|e| anyhow!("object URL: {e:?}"))?;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| anyhow!("no document"))?;
+    let a: HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(|e| anyhow!("creating link: {e:?}"))?
+        .unchecked_into();
+    a.set_href(&url);
+    a.set_download(filename);
+    a.click();
+    Url::revoke_object_url(&url).map_err(|e| anyhow!("revoking URL: {e:?}"))
+}
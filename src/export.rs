@@ -0,0 +1,71 @@
+//! File exports offered by the sidebar. Everything is computed on the CPU from
+//! a snapshot of the app state via `em_viz_core`, so the output doesn't depend
+//! on the active backend or on an in-flight ribbon compute.
+
+use crate::download;
+use crate::state::AppState;
+use crate::wgpu_renderer::{CLEAR_COLOR, RIBBON_HALF_WIDTH_PX};
+use anyhow::Result;
+use em_viz_core::scene::{FieldLine, LineKind, Scene};
+use em_viz_core::svg::{self, SvgView};
+use em_viz_core::{export, gltf};
+use leptos::prelude::*;
+
+/// Node count per axis for the field-grid export.
+pub const GRID_RES: usize = 32;
+
+/// Traced E lines as VTK PolyData with |E|, V and arc length per point.
+pub fn streamlines_vtk(app: &AppState) -> Result<()> {
+    let scene = app.scene();
+    let lines = scene.trace(LineKind::E);
+    let vtk = export::lines_to_vtk(&lines, |p| scene.potential(p), "em_viz field lines");
+    download::save_text("em_viz_lines.vtk", "text/plain", &vtk)
+}
+
+/// E, V (and B) sampled on a [`GRID_RES`]³ grid as VTK structured points.
+pub fn field_grid_vtk(app: &AppState) -> Result<()> {
+    let grid = app.scene().sample_grid(GRID_RES);
+    let vtk = export::grid_to_vtk(&grid, "em_viz field samples");
+    download::save_text("em_viz_field.vtk", "text/plain", &vtk)
+}
+
+/// The current view as SVG: the visible lines projected through the canvas
+/// camera at the canvas size, with the on-screen stroke width, charge size and
+/// background.
+pub fn view_svg(app: &AppState) -> Result<()> {
+    let scene = app.scene();
+    let lines = visible_lines(app, &scene);
+    let (width, height) = app.viewport_rt.get_untracked();
+    let bg = CLEAR_COLOR;
+    let view = SvgView {
+        view_proj: app.inv_vp.get_untracked().inverse(),
+        width,
+        height,
+        line_px: 2.0 * RIBBON_HALF_WIDTH_PX,
+        charge_px: app.point_size_px.get_untracked(),
+        colormaps: app.colormaps.get_untracked(),
+        background: Some([bg.r as f32, bg.g as f32, bg.b as f32]),
+    };
+    let out = svg::scene_svg(&scene.charges, &lines, &view);
+    download::save_text("em_viz_view.svg", "image/svg+xml", &out)
+}
+
+/// Charges as spheres and the visible ribbons as coloured tubes, as binary glTF.
+pub fn scene_glb(app: &AppState) -> Result<()> {
+    let scene = app.scene();
+    let lines = visible_lines(app, &scene);
+    let glb = gltf::scene_glb(&scene.charges, &lines, app.colormaps.get_untracked());
+    download::save_bytes("em_viz_scene.glb", "model/gltf-binary", &glb)
+}
+
+/// The lines of the layers that are switched on.
+fn visible_lines(app: &AppState, scene: &Scene) -> Vec<FieldLine> {
+    let mut lines = vec![];
+    if app.show_e.get_untracked() {
+        lines.extend(scene.trace(LineKind::E));
+    }
+    if app.show_b.get_untracked() {
+        lines.extend(scene.trace(LineKind::B));
+    }
+    lines
+}
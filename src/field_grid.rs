@@ -0,0 +1,414 @@
+use crate::perf_gpu::GpuTimerRing;
+use em_viz_core::em3d::Charge3D;
+use em_viz_core::grid::scene_bounds;
+use glam::Vec3;
+
+const BAKE_SHADER: &str = include_str!("../static/shaders/field_bake.wgsl");
+const GRID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Where the ribbon integrator gets the field from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldSource {
+    /// Superposition of all charges at every RK stage.
+    Exact,
+    /// Trilinear lookups into a grid baked once per charge change. Points outside
+    /// the grid fall back to the exact path.
+    Baked(GridSettings),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridSettings {
+    pub res: u32, // voxels per axis
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self { res: 64 }
+    }
+}
+
+impl GridSettings {
+    pub const RESOLUTIONS: [u32; 5] = [32, 48, 64, 96, 128];
+}
+
+impl FieldSource {
+    /// The grid resolution a scene file stores for this source.
+    pub fn grid_res(self) -> Option<u32> {
+        match self {
+            Self::Exact => None,
+            Self::Baked(grid) => Some(grid.res),
+        }
+    }
+
+    /// Inverse of [`Self::grid_res`]; hand-written resolutions snap to the
+    /// nearest one offered.
+    pub fn from_grid_res(res: Option<u32>) -> Self {
+        match res {
+            None => Self::Exact,
+            Some(res) => Self::Baked(GridSettings {
+                res: GridSettings::RESOLUTIONS
+                    .into_iter()
+                    .min_by_key(|r| r.abs_diff(res))
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BakeParams {
+    lo_k: [f32; 4],      // xyz = grid min corner, w = k
+    size_soft: [f32; 4], // xyz = grid extent, w = soft2
+    dims: [u32; 4],      // res, n_charges, 0, 0
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SampleParams {
+    lo_on: [f32; 4],    // xyz = grid min corner, w = 1 when the grid is in use
+    inv_size: [f32; 4], // xyz = 1 / grid extent
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BakeCharge {
+    posq: [f32; 4],
+}
+
+struct GridTextures {
+    e: wgpu::Texture, // xyz = E; there is no three-channel storage format
+}
+
+impl GridTextures {
+    fn new(device: &wgpu::Device, res: u32) -> Self {
+        Self {
+            e: device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("field grid E"),
+                size: wgpu::Extent3d {
+                    width: res,
+                    height: res,
+                    depth_or_array_layers: res,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: GRID_FORMAT,
+                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            }),
+        }
+    }
+}
+
+/// Bakes E into a 3D texture with a compute pass and exposes a
+/// bind group (`sample_layout`, group 1 of the ribbon compute) for trilinear lookups.
+pub struct FieldGrid {
+    bake_pipeline: wgpu::ComputePipeline,
+    bake_layout: wgpu::BindGroupLayout,
+    bake_ubo: wgpu::Buffer,
+    buf_charges: wgpu::Buffer,
+    charges_cap: u32,
+
+    pub sample_layout: wgpu::BindGroupLayout,
+    pub sample_bind_group: wgpu::BindGroup,
+    sample_ubo: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    textures: GridTextures,
+
+    res: u32,
+    // what the textures currently hold: charges, settings, k, soft2; None
+    // until the first bake
+    baked: Option<(Vec<Charge3D>, GridSettings, f32, f32)>,
+}
+
+impl FieldGrid {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let smod = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("field_bake"),
+            source: wgpu::ShaderSource::Wgsl(BAKE_SHADER.into()),
+        });
+
+        let storage_tex = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: GRID_FORMAT,
+                view_dimension: wgpu::TextureViewDimension::D3,
+            },
+            count: None,
+        };
+        let bake_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bake layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_tex(2),
+            ],
+        });
+
+        let bake_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("bake pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("bake pl"),
+                    bind_group_layouts: &[&bake_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            module: &smod,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let bake_ubo = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bake ubo"),
+            size: std::mem::size_of::<BakeParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let charges_cap = 64;
+        let buf_charges = Self::create_charges(device, charges_cap);
+
+        let sample_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("grid sample layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let sample_ubo = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("grid sample ubo"),
+            size: std::mem::size_of::<SampleParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("grid sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        // tiny placeholder until the first bake; `lo_on.w = 0` keeps it unused
+        let res = 1;
+        let textures = GridTextures::new(device, res);
+        let sample_bind_group = Self::create_sample_bind_group(
+            device,
+            &sample_layout,
+            &sample_ubo,
+            &textures,
+            &sampler,
+        );
+
+        Self {
+            bake_pipeline,
+            bake_layout,
+            bake_ubo,
+            buf_charges,
+            charges_cap,
+            sample_layout,
+            sample_bind_group,
+            sample_ubo,
+            sampler,
+            textures,
+            res,
+            baked: None,
+        }
+    }
+
+    fn create_charges(device: &wgpu::Device, cap: u32) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bake charges"),
+            size: (cap as u64) * std::mem::size_of::<BakeCharge>() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_sample_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        ubo: &wgpu::Buffer,
+        textures: &GridTextures,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        let e = textures.e.create_view(&Default::default());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("grid sample bg"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: ubo.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&e),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    /// Point the sampling bind group at the grid (or switch it off). Cheap; called
+    /// every dispatch so switching back to the exact field needs no rebake.
+    pub fn set_enabled(&self, queue: &wgpu::Queue, on: bool) {
+        let (lo, hi) = match &self.baked {
+            Some((charges, ..)) => scene_bounds(charges),
+            None => (Vec3::ZERO, Vec3::ONE),
+        };
+        let inv = (hi - lo).recip();
+        let params = SampleParams {
+            lo_on: [lo.x, lo.y, lo.z, (on && self.baked.is_some()) as u32 as f32],
+            inv_size: [inv.x, inv.y, inv.z, 0.0],
+        };
+        queue.write_buffer(&self.sample_ubo, 0, bytemuck::bytes_of(&params));
+    }
+
+    /// Rebake if the charges or settings changed since the last bake.
+    #[allow(clippy::too_many_arguments)]
+    pub fn bake_if_stale(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        timer: &mut GpuTimerRing,
+        charges: &[Charge3D],
+        settings: GridSettings,
+        k: f32,
+        soft2: f32,
+    ) {
+        if let Some((c, s, k0, soft0)) = &self.baked
+            && c.as_slice() == charges
+            && *s == settings
+            && (*k0, *soft0) == (k, soft2)
+        {
+            return;
+        }
+
+        let res = settings.res.max(2);
+        if res != self.res {
+            self.textures = GridTextures::new(device, res);
+            self.res = res;
+            self.sample_bind_group = Self::create_sample_bind_group(
+                device,
+                &self.sample_layout,
+                &self.sample_ubo,
+                &self.textures,
+                &self.sampler,
+            );
+        }
+
+        let n = charges.len() as u32;
+        if n > self.charges_cap {
+            self.charges_cap = n.next_power_of_two();
+            self.buf_charges = Self::create_charges(device, self.charges_cap);
+        }
+        let packed: Vec<BakeCharge> = charges
+            .iter()
+            .map(|c| BakeCharge {
+                posq: [c.pos.x, c.pos.y, c.pos.z, c.q],
+            })
+            .collect();
+        queue.write_buffer(&self.buf_charges, 0, bytemuck::cast_slice(&packed));
+
+        let (lo, hi) = scene_bounds(charges);
+        let size = hi - lo;
+        let params = BakeParams {
+            lo_k: [lo.x, lo.y, lo.z, k],
+            size_soft: [size.x, size.y, size.z, soft2],
+            dims: [res, n, 0, 0],
+        };
+        queue.write_buffer(&self.bake_ubo, 0, bytemuck::bytes_of(&params));
+
+        let e = self.textures.e.create_view(&Default::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bake bg"),
+            layout: &self.bake_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.bake_ubo.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.buf_charges.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&e),
+                },
+            ],
+        });
+
+        let mut enc = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("field bake encoder"),
+        });
+        let (ts_writes, finalize) = timer.span_compute("field bake");
+        {
+            let mut c = enc.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("field bake pass"),
+                timestamp_writes: ts_writes,
+            });
+            c.set_pipeline(&self.bake_pipeline);
+            c.set_bind_group(0, &bind_group, &[]);
+            let groups = res.div_ceil(4);
+            c.dispatch_workgroups(groups, groups, groups);
+        }
+        finalize(queue, enc);
+
+        self.baked = Some((charges.to_vec(), settings, k, soft2));
+    }
+}
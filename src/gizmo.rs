@@ -0,0 +1,281 @@
+//! The translate gizmo on the selection: hit-testing lives in
+//! `em_viz_core::gizmo`, this draws it as an SVG overlay on the canvas (so it
+//! stays out of captures and works on both renderers) with the selection's
+//! coordinates next to it while dragging. Selected charges also get a
+//! velocity arrow whose tip drags their `vel`.
+
+use crate::state::AppState;
+use em_viz_core::gizmo::{self as core_gizmo, AXES, Gizmo, Handle, PLANE_HANDLE};
+use em_viz_core::group;
+use em_viz_core::ray::ray_sphere;
+use glam::{Mat4, Vec3};
+use leptos::prelude::*;
+
+pub use em_viz_core::gizmo::snap;
+
+/// Arm length in CSS px.
+pub const ARM_PX: f32 = 80.0;
+
+/// Grid steps offered for snapping.
+pub const SNAP_STEPS: [f32; 5] = [0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// Length of a still charge's velocity stub, and the pick radius around
+/// velocity tips, in CSS px.
+const STUB_PX: f32 = 40.0;
+const TIP_PX: f32 = 7.0;
+
+const COLORS: [&str; 3] = ["#ef4444", "#22c55e", "#3b82f6"];
+const ACTIVE: &str = "#facc15";
+const VELOCITY: &str = "#e879f9";
+
+fn dpr() -> f32 {
+    window().device_pixel_ratio() as f32
+}
+
+/// The gizmo at the selection's centroid, if anything is selected. Reads its
+/// signals tracked; wrap in `untrack` from event handlers.
+pub fn current(app: &AppState) -> Option<Gizmo> {
+    let origin = app
+        .selection
+        .with(|sel| app.charges.with(|cs| group::centroid(cs, sel)))?;
+    let (_, h) = app.viewport_rt.get();
+    Some(Gizmo::new(
+        origin,
+        app.inv_vp.get().inverse(),
+        h as f32,
+        ARM_PX * dpr(),
+    ))
+}
+
+/// `(index, tip)` of the velocity arrow on every selected charge. Reads its
+/// signals tracked, like [`current`].
+pub fn velocity_tips(app: &AppState) -> Vec<(usize, Vec3)> {
+    let view_proj = app.inv_vp.get().inverse();
+    let (_, h) = app.viewport_rt.get();
+    app.selection.with(|sel| {
+        app.charges.with(|cs| {
+            sel.iter()
+                .filter_map(|&i| {
+                    let c = cs.get(i)?;
+                    let tip = core_gizmo::velocity_tip(
+                        c.pos,
+                        c.vel,
+                        view_proj,
+                        h as f32,
+                        STUB_PX * dpr(),
+                    );
+                    Some((i, tip))
+                })
+                .collect()
+        })
+    })
+}
+
+/// The velocity tip under the ray, nearest first.
+pub fn pick_velocity(app: &AppState, ro: Vec3, rd: Vec3) -> Option<(usize, Vec3)> {
+    let view_proj = app.inv_vp.get().inverse();
+    let (_, h) = app.viewport_rt.get();
+    velocity_tips(app)
+        .into_iter()
+        .filter_map(|(i, tip)| {
+            let r = TIP_PX * dpr() * core_gizmo::world_per_px(view_proj, tip, h as f32);
+            let t = ray_sphere(ro, rd, tip, r).filter(|&t| t > 0.0)?;
+            Some((i, tip, t))
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(i, tip, _)| (i, tip))
+}
+
+/// Device px of `p` on a `(w, h)` viewport; `None` behind the camera.
+fn to_px(view_proj: Mat4, (w, h): (u32, u32), p: Vec3) -> Option<(f32, f32)> {
+    let clip = view_proj * p.extend(1.0);
+    (clip.w > 0.0).then(|| {
+        let ndc = clip.truncate() / clip.w;
+        (
+            (ndc.x + 1.0) * 0.5 * w as f32,
+            (1.0 - ndc.y) * 0.5 * h as f32,
+        )
+    })
+}
+
+/// Arrowhead triangle at `tip`, pointing away from `from`, scaled by `s`.
+fn arrowhead((fx, fy): (f32, f32), (tx, ty): (f32, f32), s: f32) -> [(f32, f32); 3] {
+    let (dx, dy) = (tx - fx, ty - fy);
+    let len = (dx * dx + dy * dy).sqrt().max(1e-3);
+    let (ux, uy) = (dx / len, dy / len);
+    [
+        (tx + ux * 10.0 * s, ty + uy * 10.0 * s),
+        (tx - uy * 5.0 * s, ty + ux * 5.0 * s),
+        (tx + uy * 5.0 * s, ty - ux * 5.0 * s),
+    ]
+}
+
+/// A velocity arrow projected to device px.
+#[derive(Clone, Debug, PartialEq)]
+struct VelocityArrow {
+    idx: usize,
+    base: (f32, f32),
+    tip: (f32, f32),
+    vel: Vec3,
+}
+
+/// The gizmo projected to device px.
+#[derive(Clone, Debug, PartialEq)]
+struct Projected {
+    origin: (f32, f32),
+    tips: [(f32, f32); 3],
+    planes: [[(f32, f32); 4]; 3],
+    pos: Vec3,
+}
+
+fn project(g: &Gizmo, view_proj: Mat4, viewport: (u32, u32)) -> Option<Projected> {
+    let px = |p: Vec3| to_px(view_proj, viewport, p);
+    let (lo, hi) = PLANE_HANDLE;
+    let at = |v: Vec3| px(g.origin + v * g.size);
+    let plane = |n: usize| {
+        let (u, v) = (AXES[(n + 1) % 3], AXES[(n + 2) % 3]);
+        Some([
+            at(u * lo + v * lo)?,
+            at(u * hi + v * lo)?,
+            at(u * hi + v * hi)?,
+            at(u * lo + v * hi)?,
+        ])
+    };
+    Some(Projected {
+        origin: px(g.origin)?,
+        tips: [at(AXES[0])?, at(AXES[1])?, at(AXES[2])?],
+        planes: [plane(0)?, plane(1)?, plane(2)?],
+        pos: g.origin,
+    })
+}
+
+fn points(ps: &[(f32, f32)]) -> String {
+    ps.iter()
+        .map(|(x, y)| format!("{x:.1},{y:.1}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[component]
+pub fn GizmoOverlay(app: AppState) -> impl IntoView {
+    let projected = Memo::new(move |_| {
+        let g = current(&app)?;
+        project(&g, app.inv_vp.get().inverse(), app.viewport_rt.get())
+    });
+    let velocities = Memo::new(move |_| {
+        let view_proj = app.inv_vp.get().inverse();
+        let viewport = app.viewport_rt.get();
+        let tips = velocity_tips(&app);
+        app.charges.with(|cs| {
+            tips.into_iter()
+                .filter_map(|(idx, tip)| {
+                    let c = cs.get(idx)?;
+                    Some(VelocityArrow {
+                        idx,
+                        base: to_px(view_proj, viewport, c.pos)?,
+                        tip: to_px(view_proj, viewport, tip)?,
+                        vel: c.vel,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    let handle = Memo::new(move |_| {
+        let d = app.drag.get();
+        d.active.then_some(d.handle).flatten()
+    });
+    // moving the selection, vs. steering a charge by its velocity tip
+    let dragging = Memo::new(move |_| {
+        let d = app.drag.get();
+        d.active && d.velocity.is_none()
+    });
+    let steering = Memo::new(move |_| {
+        let d = app.drag.get();
+        d.active.then_some(d.velocity).flatten()
+    });
+    let color = move |h: Handle, axis: usize| {
+        if handle.get() == Some(h) {
+            ACTIVE
+        } else {
+            COLORS[axis]
+        }
+    };
+
+    view! {
+      <svg
+        class="absolute inset-0 w-full h-full pointer-events-none"
+        viewBox=move || {
+          let (w, h) = app.viewport_rt.get();
+          format!("0 0 {w} {h}")
+        }
+        preserveAspectRatio="none"
+      >
+        {move || projected.get().map(|p| {
+          let s = dpr();
+          let (ox, oy) = p.origin;
+          let planes = (0..3).map(|n| view! {
+            <polygon
+              points=points(&p.planes[n])
+              fill=move || color(Handle::Plane(n), n)
+              fill-opacity="0.35"
+            />
+          }).collect_view();
+          let arrows = (0..3).map(|a| {
+            let (tx, ty) = p.tips[a];
+            // arrowhead along the projected axis
+            let head = arrowhead(p.origin, p.tips[a], s);
+            view! {
+              <line x1=ox y1=oy x2=tx y2=ty
+                stroke=move || color(Handle::Axis(a), a)
+                stroke-width=2.5 * s/>
+              <polygon points=points(&head) fill=move || color(Handle::Axis(a), a)/>
+            }
+          }).collect_view();
+          let readout = dragging.get().then(|| view! {
+            <text x=ox + 14.0 * s y=oy - 14.0 * s
+              font-size=12.0 * s font-family="monospace" fill="white"
+              stroke="black" stroke-width=3.0 * s paint-order="stroke"
+            >
+              {format!("x {:.3}  y {:.3}  z {:.3}", p.pos.x, p.pos.y, p.pos.z)}
+            </text>
+          });
+          view! { {planes} {arrows} {readout} }
+        })}
+        {move || {
+          let s = dpr();
+          let active = steering.get();
+          velocities.get().into_iter().map(|v| {
+            let (bx, by) = v.base;
+            let (tx, ty) = v.tip;
+            let color = if active == Some(v.idx) { ACTIVE } else { VELOCITY };
+            let still = v.vel.length_squared() <= 1e-10;
+            // still charges get a dashed stub ending in a ring to drag out
+            let tip = if still {
+              view! {
+                <circle cx=tx cy=ty r=5.0 * s fill="none" stroke=color stroke-width=2.0 * s/>
+              }.into_any()
+            } else {
+              view! { <polygon points=points(&arrowhead(v.base, v.tip, s)) fill=color/> }.into_any()
+            };
+            let readout = (active == Some(v.idx)).then(|| view! {
+              <text x=tx + 14.0 * s y=ty - 14.0 * s
+                font-size=12.0 * s font-family="monospace" fill="white"
+                stroke="black" stroke-width=3.0 * s paint-order="stroke"
+              >
+                {format!(
+                  "v {:.3} {:.3} {:.3}  |v| {:.3}",
+                  v.vel.x, v.vel.y, v.vel.z, v.vel.length()
+                )}
+              </text>
+            });
+            view! {
+              <line x1=bx y1=by x2=tx y2=ty stroke=color stroke-width=2.0 * s
+                stroke-dasharray=if still { "4 3" } else { "none" }/>
+              {tip}
+              {readout}
+            }
+          }).collect_view()
+        }}
+      </svg>
+    }
+}
@@ -0,0 +1,83 @@
+//! Collects GPU errors (uncaptured, error scopes, device loss) from wgpu callbacks
+//! so the RAF loop can surface them in the UI. The sink outlives any one device:
+//! after a loss the renderer is rebuilt and reattached to the same sink.
+
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use wgpu::{DeviceLostReason, ErrorFilter};
+
+#[derive(Default)]
+struct Inner {
+    pending: Vec<String>,
+    lost: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct ErrorSink {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl ErrorSink {
+    pub fn push(&self, source: &str, msg: impl Display) {
+        let msg = format!("[{source}] {msg}");
+        let mut inner = self.inner.lock().unwrap();
+        // the same failure tends to repeat every frame
+        if inner.pending.last() != Some(&msg) {
+            inner.pending.push(msg);
+        }
+    }
+
+    /// Messages collected since the last call.
+    pub fn drain(&self) -> Vec<String> {
+        std::mem::take(&mut self.inner.lock().unwrap().pending)
+    }
+
+    /// Returns the loss message once if the device has been lost.
+    pub fn take_lost(&self) -> Option<String> {
+        self.inner.lock().unwrap().lost.take()
+    }
+
+    /// Route uncaptured errors and device loss on `device` into this sink.
+    pub fn install(&self, device: &wgpu::Device) {
+        let sink = self.clone();
+        device.on_uncaptured_error(Arc::new(move |e: wgpu::Error| sink.push("uncaptured", e)));
+
+        let sink = self.clone();
+        device.set_device_lost_callback(move |reason, msg| {
+            // `Destroyed` is us dropping the old device during a rebuild
+            if reason == DeviceLostReason::Destroyed {
+                return;
+            }
+            let msg = format!("device lost ({reason:?}): {msg}");
+            sink.push("device", &msg);
+            sink.inner.lock().unwrap().lost = Some(msg);
+        });
+    }
+}
+
+/// Validation + out-of-memory error scope around one submission. `pop` resolves
+/// the scopes asynchronously and pushes anything caught into the sink.
+#[must_use]
+pub struct ErrorScope {
+    label: &'static str,
+}
+
+impl ErrorScope {
+    pub fn push(device: &wgpu::Device, label: &'static str) -> Self {
+        device.push_error_scope(ErrorFilter::OutOfMemory);
+        device.push_error_scope(ErrorFilter::Validation);
+        Self { label }
+    }
+
+    pub fn pop(self, device: &wgpu::Device, sink: &ErrorSink) {
+        let validation = device.pop_error_scope();
+        let oom = device.pop_error_scope();
+        let sink = sink.clone();
+        let label = self.label;
+        wasm_bindgen_futures::spawn_local(async move {
+            for e in [validation.await, oom.await].into_iter().flatten() {
+                sink.push(label, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,219 @@
+//! Undo / redo for scene edits. Every change the user makes goes through here
+//! as an [`Edit`] (applied, then recorded in `app.history`); Ctrl+Z and
+//! Ctrl+Shift+Z / Ctrl+Y walk the stack. Camera moves and live motion are not
+//! edits.
+
+use crate::state::AppState;
+use em_viz_core::history::{ChargeEdit, Coalesce};
+use em_viz_core::scene_file::SceneFile;
+use leptos::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::closure::Closure;
+use web_sys::{HtmlInputElement, KeyboardEvent};
+
+/// Undo steps kept.
+pub const LIMIT: usize = 200;
+
+/// Changes to one control closer together than this are one step.
+const SETTLE_MS: f64 = 1000.0;
+
+#[derive(Clone, Debug)]
+pub enum Edit {
+    /// Charges added, removed or changed; the steps of one drag share a
+    /// `gesture`.
+    Charges {
+        edit: ChargeEdit,
+        gesture: Option<u32>,
+    },
+    /// Settings before and after (see [`AppState::settings`]).
+    Settings {
+        control: &'static str,
+        before: Box<SceneFile>,
+        after: Box<SceneFile>,
+        at_ms: f64,
+    },
+    /// The whole scene replaced (presets, files, workspaces), camera aside.
+    Scene {
+        before: Box<SceneFile>,
+        after: Box<SceneFile>,
+    },
+}
+
+impl Coalesce for Edit {
+    fn coalesce(&mut self, next: Self) -> Result<(), Self> {
+        match (self, next) {
+            (
+                Self::Charges {
+                    edit,
+                    gesture: Some(g),
+                },
+                Self::Charges {
+                    edit: next,
+                    gesture: Some(h),
+                },
+            ) if *g == h => edit.coalesce(next).map_err(|next| Self::Charges {
+                edit: next,
+                gesture: Some(h),
+            }),
+            (
+                Self::Settings {
+                    control,
+                    after,
+                    at_ms,
+                    ..
+                },
+                Self::Settings {
+                    control: c,
+                    after: a,
+                    at_ms: t,
+                    ..
+                },
+            ) if *control == c && t - *at_ms < SETTLE_MS => {
+                *after = a;
+                *at_ms = t;
+                Ok(())
+            }
+            (_, next) => Err(next),
+        }
+    }
+}
+
+impl Edit {
+    fn apply(&self, app: &AppState) {
+        match self {
+            Self::Charges { edit, .. } => {
+                app.charges.update(|cs| edit.apply(cs));
+                follow_selection(app, edit, false);
+            }
+            Self::Settings { after, .. } => app.set_settings(after),
+            Self::Scene { after, .. } => app.apply_file(after),
+        }
+    }
+
+    fn revert(&self, app: &AppState) {
+        match self {
+            Self::Charges { edit, .. } => {
+                app.charges.update(|cs| edit.revert(cs));
+                follow_selection(app, edit, true);
+            }
+            Self::Settings { before, .. } => app.set_settings(before),
+            Self::Scene { before, .. } => app.apply_file(before),
+        }
+    }
+}
+
+/// Keep `app.selection` on the same charges after `edit` (or, with
+/// `reverted`, its undo) shifted the list; a replaced list clears it.
+fn follow_selection(app: &AppState, edit: &ChargeEdit, reverted: bool) {
+    match (edit, reverted) {
+        (ChargeEdit::Insert { idx, .. }, false) | (ChargeEdit::Remove { idx, .. }, true) => {
+            app.selection.update(|sel| {
+                for s in sel.iter_mut().filter(|s| **s >= *idx) {
+                    *s += 1;
+                }
+            });
+        }
+        (ChargeEdit::Insert { idx, .. }, true) | (ChargeEdit::Remove { idx, .. }, false) => {
+            app.selection.update(|sel| {
+                sel.retain(|s| s != idx);
+                for s in sel.iter_mut().filter(|s| **s > *idx) {
+                    *s -= 1;
+                }
+            });
+        }
+        (ChargeEdit::Replace { .. }, _) => app.selection.set(vec![]),
+        (ChargeEdit::Update(_), _) => {}
+    }
+}
+
+fn record(app: &AppState, edit: Edit) {
+    app.history.update(|h| h.record(edit));
+}
+
+/// Apply a charge edit as one undo step, or as part of drag `gesture`.
+pub fn edit_charges(app: &AppState, edit: ChargeEdit, gesture: Option<u32>) {
+    let edit = Edit::Charges { edit, gesture };
+    edit.apply(app);
+    record(app, edit);
+}
+
+/// Run `change`, which sets settings signals, as an undo step; quick
+/// successive changes to the same `control` (a slider drag) merge.
+pub fn change_settings(app: &AppState, control: &'static str, change: impl FnOnce()) {
+    let before = app.settings();
+    change();
+    let after = app.settings();
+    if before != after {
+        record(
+            app,
+            Edit::Settings {
+                control,
+                before: Box::new(before),
+                after: Box::new(after),
+                at_ms: now_ms(),
+            },
+        );
+    }
+}
+
+/// Load `file` as an undoable step. The camera moves but isn't part of it.
+pub fn open(app: &AppState, file: SceneFile) {
+    let before = app.scene_file();
+    app.apply_file(&file);
+    let after = app.scene_file();
+    record(
+        app,
+        Edit::Scene {
+            before: Box::new(SceneFile {
+                camera: None,
+                ..before
+            }),
+            after: Box::new(SceneFile {
+                camera: None,
+                ..after
+            }),
+        },
+    );
+}
+
+pub fn undo(app: &AppState) {
+    if let Some(edit) = app.history.try_update(|h| h.undo().cloned()).flatten() {
+        edit.revert(app);
+    }
+}
+
+pub fn redo(app: &AppState) {
+    if let Some(edit) = app.history.try_update(|h| h.redo().cloned()).flatten() {
+        edit.apply(app);
+    }
+}
+
+/// Ctrl/Cmd+Z undoes, Ctrl/Cmd+Shift+Z and Ctrl+Y redo. Text fields keep their
+/// own undo.
+pub fn attach_keys(app: AppState) {
+    let on_key = Closure::<dyn FnMut(_)>::new(move |e: KeyboardEvent| {
+        if !(e.ctrl_key() || e.meta_key()) || e.alt_key() || typing(&e) {
+            return;
+        }
+        match e.key().to_ascii_lowercase().as_str() {
+            "z" if e.shift_key() => redo(&app),
+            "z" => undo(&app),
+            "y" => redo(&app),
+            _ => return,
+        }
+        e.prevent_default();
+    });
+    let _ = window().add_event_listener_with_callback("keydown", on_key.as_ref().unchecked_ref());
+    on_key.forget();
+}
+
+/// Whether `e` goes to a text field, which handles its own editing keys.
+pub fn typing(e: &KeyboardEvent) -> bool {
+    e.target()
+        .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        .is_some_and(|input| matches!(input.type_().as_str(), "text" | "number"))
+}
+
+fn now_ms() -> f64 {
+    window().performance().map_or(0.0, |p| p.now())
+}
@@ -1,12 +1,23 @@
 mod app;
 mod camera;
-mod em3d;
+mod capture;
+mod download;
+mod export;
+mod field_grid;
+mod gizmo;
+mod gpu_errors;
+mod history;
 mod perf;
 mod perf_gpu;
 mod picking;
-mod seed;
+mod record;
+mod renderer;
+mod scene_io;
+mod share_link;
 mod state;
+mod webgl_renderer;
 mod wgpu_renderer;
+mod workspaces;
 
 use app::App;
 use leptos::{logging, mount};
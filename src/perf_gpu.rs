@@ -10,15 +10,61 @@ struct Slot {
     read_buf: wgpu::Buffer,
 }
 
-pub struct GpuTimerRing {
+/// Where span timings come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimingSource {
+    /// GPU timestamp queries around each pass.
+    Timestamps,
+    /// CPU time from `queue.submit` to the `on_submitted_work_done` callback;
+    /// includes queueing, so it overestimates the pass itself.
+    SubmitToDone,
+}
+
+impl TimingSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Timestamps => "GPU timestamps",
+            Self::SubmitToDone => "submit→done (CPU)",
+        }
+    }
+}
+
+struct Timestamps {
     qset: wgpu::QuerySet,
     slots: [Slot; NUM_SLOTS],
-    head: usize,
     period_ns: f64, // on webgpu always 1 ns.. on other platforms timer ticks can be different
 }
 
+/// Per-pass timings. Uses timestamp queries when the device has
+/// `Features::TIMESTAMP_QUERY`, otherwise falls back to [`TimingSource::SubmitToDone`].
+pub struct GpuTimerRing {
+    ts: Option<Timestamps>,
+    head: usize,
+}
+
 impl GpuTimerRing {
     pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, label: &'static str) -> Self {
+        let ts = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| Timestamps::new(device, queue, label));
+        if ts.is_none() {
+            log!("{label}: no timestamp queries, timing submit→done instead");
+        }
+        Self { ts, head: 0 }
+    }
+
+    pub fn source(&self) -> TimingSource {
+        if self.ts.is_some() {
+            TimingSource::Timestamps
+        } else {
+            TimingSource::SubmitToDone
+        }
+    }
+}
+
+impl Timestamps {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, label: &'static str) -> Self {
         let qset = device.create_query_set(&wgpu::QuerySetDescriptor {
             label: Some(&format!("{label}.qset")),
             ty: wgpu::QueryType::Timestamp,
@@ -49,10 +95,10 @@ impl GpuTimerRing {
         Self {
             qset,
             slots,
-            head: 0,
             period_ns: queue.get_timestamp_period() as f64,
         }
     }
+
     fn span_inner<'a>(
         &'a self,
         idx: usize,
@@ -105,26 +151,47 @@ impl GpuTimerRing {
 
         (begin_q, end_q, finalize)
     }
+}
+
+/// Fallback finalize: time from submit until the queue reports the work done.
+fn submit_to_done(label: &'static str, queue: &wgpu::Queue, encoder: wgpu::CommandEncoder) {
+    let t0 = perf::now_ms();
+    queue.submit(Some(encoder.finish()));
+    queue.on_submitted_work_done(move || perf::record_timing(label, perf::now_ms() - t0));
+}
+
+impl GpuTimerRing {
+    fn next_slot(&mut self) -> usize {
+        // Notice that this is kinda racy... we rely on slots to be empty before we modulo around to them again!
+        let idx = self.head;
+        self.head = (idx + 1) % NUM_SLOTS;
+        idx
+    }
 
     pub fn span_compute<'a>(
         &'a mut self,
         label: &'static str,
     ) -> (
-        ComputePassTimestampWrites<'a>,
+        Option<ComputePassTimestampWrites<'a>>,
         impl FnOnce(&wgpu::Queue, wgpu::CommandEncoder) + 'a,
     ) {
-        // Notice that this is kinda racy... we rely on slots to be empty before we modulo around to them again!
-        let idx = self.head;
-        self.head = (idx + 1) % NUM_SLOTS;
-
-        let (begin_q, end_q, finalize) = self.span_inner(idx, label);
-
-        let ts_writes = ComputePassTimestampWrites {
-            query_set: &self.qset,
-            beginning_of_pass_write_index: Some(begin_q),
-            end_of_pass_write_index: Some(end_q),
+        let idx = self.next_slot();
+        let (ts_writes, resolve) = match &self.ts {
+            Some(ts) => {
+                let (begin_q, end_q, finalize) = ts.span_inner(idx, label);
+                let ts_writes = ComputePassTimestampWrites {
+                    query_set: &ts.qset,
+                    beginning_of_pass_write_index: Some(begin_q),
+                    end_of_pass_write_index: Some(end_q),
+                };
+                (Some(ts_writes), Some(finalize))
+            }
+            None => (None, None),
+        };
+        let finalize = move |queue: &wgpu::Queue, encoder: wgpu::CommandEncoder| match resolve {
+            Some(f) => f(queue, encoder),
+            None => submit_to_done(label, queue, encoder),
         };
-
         (ts_writes, finalize)
     }
 
@@ -132,21 +199,26 @@ impl GpuTimerRing {
         &'a mut self,
         label: &'static str,
     ) -> (
-        RenderPassTimestampWrites<'a>,
+        Option<RenderPassTimestampWrites<'a>>,
         impl FnOnce(&wgpu::Queue, wgpu::CommandEncoder) + 'a,
     ) {
-        // Notice that this is kinda racy... we rely on slots to be empty before we modulo around to them again!
-        let idx = self.head;
-        self.head = (idx + 1) % NUM_SLOTS;
-
-        let (begin_q, end_q, finalize) = self.span_inner(idx, label);
-
-        let ts_writes = RenderPassTimestampWrites {
-            query_set: &self.qset,
-            beginning_of_pass_write_index: Some(begin_q),
-            end_of_pass_write_index: Some(end_q),
+        let idx = self.next_slot();
+        let (ts_writes, resolve) = match &self.ts {
+            Some(ts) => {
+                let (begin_q, end_q, finalize) = ts.span_inner(idx, label);
+                let ts_writes = RenderPassTimestampWrites {
+                    query_set: &ts.qset,
+                    beginning_of_pass_write_index: Some(begin_q),
+                    end_of_pass_write_index: Some(end_q),
+                };
+                (Some(ts_writes), Some(finalize))
+            }
+            None => (None, None),
+        };
+        let finalize = move |queue: &wgpu::Queue, encoder: wgpu::CommandEncoder| match resolve {
+            Some(f) => f(queue, encoder),
+            None => submit_to_done(label, queue, encoder),
         };
-
         (ts_writes, finalize)
     }
 }
@@ -1,49 +1,28 @@
 // use crate::perf::Scope;
-use crate::state::{AppState, Drag3D};
+use crate::gizmo;
+use crate::history;
+use crate::state::{AppState, Drag3D, Marquee};
+use em_viz_core::em3d::Charge3D;
+use em_viz_core::gizmo::{VEL_SCALE, snap_to_axis};
+use em_viz_core::group;
+use em_viz_core::history::{ChargeChange, ChargeEdit};
+use em_viz_core::ray::{self, ray_from_ndc_with_inv, ray_plane};
 use glam::Vec3;
 use leptos::prelude::*;
 // use std::{cell::RefCell, rc::Rc};
 use wasm_bindgen::JsCast;
-use web_sys::PointerEvent;
+use web_sys::{KeyboardEvent, PointerEvent};
 
-// --- minimal picking helpers (you can keep yours)
+// --- minimal picking helpers; the ray math lives in `em_viz_core::ray`
 pub fn screen_to_ndc(x: f32, y: f32, rect: &web_sys::DomRect) -> (f32, f32) {
-    let nx = ((x as f64 - rect.left()) / rect.width()) as f32;
-    let ny = ((y as f64 - rect.top()) / rect.height()) as f32;
-    (nx * 2.0 - 1.0, 1.0 - ny * 2.0)
-}
-
-pub fn ray_from_ndc_with_inv(ndc: (f32, f32), inv_vp: glam::Mat4, eye: Vec3) -> (Vec3, Vec3) {
-    let p_ndc = glam::Vec4::new(ndc.0, ndc.1, 0.0, 1.0);
-    let q_ndc = glam::Vec4::new(ndc.0, ndc.1, 1.0, 1.0);
-    let p = inv_vp * p_ndc;
-    let q = inv_vp * q_ndc;
-    let p = (p.truncate() / p.w).extend(1.0).truncate();
-    let q = (q.truncate() / q.w).extend(1.0).truncate();
-    let ro = eye;
-    let rd = (q - p).normalize_or_zero();
-    (ro, rd)
-}
-
-pub fn ray_sphere(ro: Vec3, rd: Vec3, c: Vec3, r: f32) -> Option<f32> {
-    let oc = ro - c;
-    let b = oc.dot(rd);
-    let c2 = oc.dot(oc) - r * r;
-    let disc = b * b - c2;
-    if disc < 0.0 {
-        return None;
-    }
-    let t = -b - disc.sqrt();
-    if t > 0.0 { Some(t) } else { None }
-}
-
-pub fn ray_plane(ro: Vec3, rd: Vec3, p0: Vec3, n: Vec3) -> Option<f32> {
-    let denom = rd.dot(n);
-    if denom.abs() < 1e-6 {
-        return None;
-    }
-    let t = (p0 - ro).dot(n) / denom;
-    if t > 0.0 { Some(t) } else { None }
+    ray::screen_to_ndc(
+        x,
+        y,
+        rect.left() as f32,
+        rect.top() as f32,
+        rect.width() as f32,
+        rect.height() as f32,
+    )
 }
 
 // --- public API
@@ -60,7 +39,9 @@ pub fn attach(canvas_ref: NodeRef<leptos::html::Canvas>, app: AppState) {
         .unwrap();
     on_ctx.forget();
 
-    // pointerdown (LMB picks a charge)
+    // pointerdown: LMB selects and drags a charge (the whole selection if it is
+    // part of it), a gizmo handle or a velocity tip, or starts a box select on
+    // empty space; Ctrl/Cmd toggles, Shift adds a charge
     let on_down = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(move |e: PointerEvent| {
         if e.button() != 0 {
             return;
@@ -76,31 +57,105 @@ pub fn attach(canvas_ref: NodeRef<leptos::html::Canvas>, app: AppState) {
         let ndc = screen_to_ndc(e.client_x() as f32, e.client_y() as f32, &rect);
         let (ro, rd) = ray_from_ndc_with_inv(ndc, inv_vp, eye);
 
-        let cs = app.charges.get_untracked();
-        let pick_r = 0.3;
-        let mut best: Option<(usize, f32)> = None;
-        for (i, c) in cs.iter().enumerate() {
-            if let Some(t) = ray_sphere(ro, rd, c.pos, pick_r)
-                && t > 0.0
-                && best.is_none_or(|(_, b)| t < b)
-            {
-                best = Some((i, t));
+        if e.shift_key() {
+            // on the plane through the orbit target, facing the camera
+            let target = app.orbit_rt.get_untracked().target;
+            if let Some(t) = ray_plane(ro, rd, target, (target - eye).normalize_or_zero()) {
+                app.add_charge(Charge3D {
+                    pos: ro + rd * t,
+                    q: 1.0,
+                    vel: Vec3::ZERO,
+                });
             }
+            e.prevent_default();
+            return;
         }
-        if let Some((idx, t)) = best {
-            // draggable plane: through hit point, facing the camera
-            // use camera forward from inv(view); simplest good proxy is ray dir.
+
+        let toggle = e.ctrl_key() || e.meta_key();
+        let start = |drag: Drag3D| {
+            app.drag.update(|d| {
+                *d = Drag3D {
+                    active: true,
+                    gesture: d.gesture.wrapping_add(1),
+                    ..drag
+                }
+            });
+            let _ = canvas_ref
+                .get_untracked()
+                .unwrap()
+                .set_pointer_capture(e.pointer_id());
+            e.prevent_default();
+        };
+
+        // velocity tips, then gizmo handles, sit in front of the charges
+        if !toggle && let Some((idx, tip)) = untrack(|| gizmo::pick_velocity(&app, ro, rd)) {
+            start(Drag3D {
+                velocity: Some(idx),
+                plane_p: tip,
+                plane_n: rd,
+                ..Drag3D::default()
+            });
+            return;
+        }
+        if !toggle
+            && let Some(g) = untrack(|| gizmo::current(&app))
+            && let Some(handle) = g.pick(ro, rd)
+            && let Some(grab) = handle.point(g.origin, ro, rd)
+        {
+            start(Drag3D {
+                handle: Some(handle),
+                origin: g.origin,
+                grab,
+                ..Drag3D::default()
+            });
+            return;
+        }
+
+        // the disc each charge is drawn as, nearest first where they overlap
+        let cs = app.charges.get_untracked();
+        let (w, h) = app.viewport_rt.get_untracked();
+        let best = ray::pick_disc(
+            cs.iter().map(|c| c.pos),
+            inv_vp.inverse(),
+            ndc,
+            (w as f32, h as f32),
+            0.5 * app.point_size_px.get_untracked(),
+        )
+        .map(|idx| (idx, (cs[idx].pos - ro).dot(rd)));
+        if let Some((idx, _)) = best
+            && toggle
+        {
+            app.select(idx, true);
+            e.prevent_default();
+        } else if let Some((idx, t)) = best {
+            // draggable plane: through the point on the ray level with the
+            // charge, facing the camera (the ray dir stands in for forward)
             let hit = ro + rd * t;
             let fwd = rd;
-
-            app.drag.set(Drag3D {
-                active: true,
-                idx,
+            if !app.selection.with_untracked(|sel| sel.contains(&idx)) {
+                app.select(idx, false);
+            }
+            let origin = app
+                .selection
+                .with_untracked(|sel| group::centroid(&cs, sel))
+                .unwrap_or(hit);
+            start(Drag3D {
+                handle: None,
                 plane_p: hit,
                 plane_n: fwd,
-                hit_offset: Vec3::ZERO,
+                origin,
+                grab: hit,
+                ..Drag3D::default()
             });
-
+        } else {
+            if !toggle {
+                app.selection.set(vec![]);
+            }
+            let at = (
+                e.client_x() as f32 - rect.left() as f32,
+                e.client_y() as f32 - rect.top() as f32,
+            );
+            app.marquee.set(Some(Marquee { start: at, end: at }));
             let _ = canvas_ref
                 .get_untracked()
                 .unwrap()
@@ -117,6 +172,23 @@ pub fn attach(canvas_ref: NodeRef<leptos::html::Canvas>, app: AppState) {
 
     // let rebuild_debounce_move = rebuild_debounce.clone();
     let on_move = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(move |e: PointerEvent| {
+        let rect = canvas_ref
+            .get_untracked()
+            .expect("canvas")
+            .get_bounding_client_rect();
+        if app.marquee.with_untracked(Option::is_some) {
+            let at = (
+                e.client_x() as f32 - rect.left() as f32,
+                e.client_y() as f32 - rect.top() as f32,
+            );
+            app.marquee.update(|m| {
+                if let Some(m) = m {
+                    m.end = at;
+                }
+            });
+            return;
+        }
+
         let d = app.drag.get_untracked();
         if !d.active {
             return;
@@ -125,25 +197,68 @@ pub fn attach(canvas_ref: NodeRef<leptos::html::Canvas>, app: AppState) {
         let eye = app.eye_rt.get_untracked();
         let inv_vp = app.inv_vp.get_untracked();
 
-        let rect = canvas_ref
-            .get_untracked()
-            .expect("canvas")
-            .get_bounding_client_rect();
         let ndc = screen_to_ndc(e.client_x() as f32, e.client_y() as f32, &rect);
         let (ro, rd) = ray_from_ndc_with_inv(ndc, inv_vp, eye);
 
-        if let Some(t) = ray_plane(ro, rd, d.plane_p, d.plane_n) {
-            let p = ro + rd * t + d.hit_offset;
+        if let Some(idx) = d.velocity {
+            // the tip follows the pointer; Shift keeps only the dominant axis
+            let Some(t) = ray_plane(ro, rd, d.plane_p, d.plane_n) else {
+                return;
+            };
+            let Some(before) = app.charges.with_untracked(|cs| cs.get(idx).copied()) else {
+                return;
+            };
+            let mut vel = (ro + rd * t - before.pos) / VEL_SCALE;
+            if e.shift_key() {
+                vel = snap_to_axis(vel);
+            }
+            let change = ChargeChange {
+                idx,
+                before,
+                after: Charge3D { vel, ..before },
+            };
+            history::edit_charges(&app, ChargeEdit::Update(vec![change]), Some(d.gesture));
+            e.prevent_default();
+            return;
+        }
 
-            // write directly; this triggers the upload_charges effect
-            app.charges.update(|cs| {
-                if let Some(ch) = cs.get_mut(d.idx) {
-                    ch.pos = p;
-                }
-            });
+        let under = match d.handle {
+            Some(h) => h.point(d.origin, ro, rd),
+            None => ray_plane(ro, rd, d.plane_p, d.plane_n).map(|t| ro + rd * t),
+        };
+        if let Some(p) = under {
+            let mut target = d.origin + (p - d.grab);
+            if let Some(step) = app.snap.get_untracked() {
+                target = gizmo::snap(target, step, d.handle);
+            }
 
-            // mark that we owe a rebuild when dragging stops
-            // app.pending_rebuild.set(true);
+            // the selection's centroid follows the pointer; one undo step per
+            // drag, and the charges signal drives the upload
+            let changes = app.selection.with_untracked(|sel| {
+                app.charges.with_untracked(|cs| {
+                    let Some(now) = group::centroid(cs, sel) else {
+                        return vec![];
+                    };
+                    let delta = target - now;
+                    sel.iter()
+                        .filter_map(|&i| {
+                            let before = *cs.get(i)?;
+                            let after = Charge3D {
+                                pos: before.pos + delta,
+                                ..before
+                            };
+                            Some(ChargeChange {
+                                idx: i,
+                                before,
+                                after,
+                            })
+                        })
+                        .collect()
+                })
+            });
+            if !changes.is_empty() {
+                history::edit_charges(&app, ChargeEdit::Update(changes), Some(d.gesture));
+            }
         }
         e.prevent_default();
     });
@@ -156,6 +271,23 @@ pub fn attach(canvas_ref: NodeRef<leptos::html::Canvas>, app: AppState) {
     // pointerup/cancel
     let on_up = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(move |_e: PointerEvent| {
         app.drag.update(|d| d.active = false);
+        if let Some(m) = app.marquee.get_untracked() {
+            app.marquee.set(None);
+            let (w, h) = canvas_ref
+                .get_untracked()
+                .map(|c| (c.client_width() as f32, c.client_height() as f32))
+                .unwrap_or((1.0, 1.0));
+            let ndc = |(x, y): (f32, f32)| ray::screen_to_ndc(x, y, 0.0, 0.0, w, h);
+            let view_proj = app.inv_vp.get_untracked().inverse();
+            let boxed = app
+                .charges
+                .with_untracked(|cs| group::in_ndc_box(cs, view_proj, ndc(m.start), ndc(m.end)));
+            app.selection.update(|sel| {
+                sel.extend(boxed);
+                sel.sort_unstable();
+                sel.dedup();
+            });
+        }
 
         // Mark that we owe a rebuild; if we're idle, kick it now; if not, the
         // compute effect will see `pending_rebuild` and run one more when done.
@@ -171,4 +303,25 @@ pub fn attach(canvas_ref: NodeRef<leptos::html::Canvas>, app: AppState) {
     w.add_event_listener_with_callback("pointercancel", on_up.as_ref().unchecked_ref())
         .unwrap();
     on_up.forget();
+
+    // Delete/Backspace removes the selection, Escape clears it, Ctrl/Cmd+A
+    // selects every charge
+    let on_key = wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(move |e: KeyboardEvent| {
+        if history::typing(&e) {
+            return;
+        }
+        match e.key().as_str() {
+            "Delete" | "Backspace" => app.delete_selection(),
+            "Escape" => app.selection.set(vec![]),
+            "a" | "A" if e.ctrl_key() || e.meta_key() => {
+                app.selection
+                    .set((0..app.charges.with_untracked(Vec::len)).collect());
+            }
+            _ => return,
+        }
+        e.prevent_default();
+    });
+    w.add_event_listener_with_callback("keydown", on_key.as_ref().unchecked_ref())
+        .unwrap();
+    on_key.forget();
 }
@@ -0,0 +1,304 @@
+//! Frame-sequence export. While a recording runs, the RAF loop stands aside and
+//! this task drives the renderer itself: each frame advances the motion by
+//! exactly 1 / fps, waits for that frame's ribbons, then captures it. Nothing is
+//! dropped however long a frame takes.
+
+use crate::camera::{Camera, OrbitController};
+use crate::capture::{self, CaptureOptions};
+use crate::download;
+use crate::renderer::Renderer;
+use crate::state::AppState;
+use anyhow::{Context, Result, anyhow, bail};
+use em_viz_core::zip::ZipWriter;
+use futures_channel::oneshot;
+use leptos::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::closure::Closure;
+use web_sys::{
+    Blob, BlobEvent, BlobPropertyBag, CanvasCaptureMediaStreamTrack, HtmlCanvasElement,
+    MediaRecorder, MediaRecorderOptions, RecordingState,
+};
+
+type RendererSignal = RwSignal<Option<Renderer>, LocalStorage>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// Numbered PNGs from offscreen captures (WebGPU only).
+    PngZip,
+    /// MediaRecorder on the canvas stream, paced to real time.
+    WebM,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordOptions {
+    /// Size, MSAA, supersampling and background of each frame. WebM uses only
+    /// the size (the canvas is resized for the recording).
+    pub frame: CaptureOptions,
+    pub fps: u32,
+    pub seconds: f32,
+    pub format: RecordFormat,
+}
+
+impl Default for RecordOptions {
+    fn default() -> Self {
+        Self {
+            frame: CaptureOptions {
+                width: 1920,
+                height: 1080,
+                supersample: 1,
+                ..CaptureOptions::default()
+            },
+            fps: 30,
+            seconds: 4.0,
+            format: RecordFormat::PngZip,
+        }
+    }
+}
+
+impl RecordOptions {
+    pub const FPS: [u32; 4] = [24, 25, 30, 60];
+
+    pub fn frame_count(&self) -> u32 {
+        (self.seconds * self.fps as f32).round().max(1.0) as u32
+    }
+}
+
+/// Record `opts`, then save the result and clear `app.recording`. The RAF loop
+/// sets `recording` before spawning this.
+pub async fn record(
+    renderer_sig: RendererSignal,
+    orbit_ctl: Rc<RefCell<OrbitController>>,
+    canvas: HtmlCanvasElement,
+    app: AppState,
+    opts: RecordOptions,
+) {
+    let result = match opts.format {
+        RecordFormat::PngZip => record_zip(renderer_sig, &orbit_ctl, app, &opts).await,
+        RecordFormat::WebM => record_webm(renderer_sig, &orbit_ctl, &canvas, app, &opts).await,
+    };
+    app.recording.set(false);
+    app.record_status.set(Some(match result {
+        Ok(msg) => msg,
+        Err(e) => format!("recording stopped: {e}"),
+    }));
+}
+
+async fn record_zip(
+    renderer_sig: RendererSignal,
+    orbit_ctl: &Rc<RefCell<OrbitController>>,
+    app: AppState,
+    opts: &RecordOptions,
+) -> Result<String> {
+    let n = opts.frame_count();
+    let mut zip = ZipWriter::new();
+    for i in 0..n {
+        let (view, proj) = step(renderer_sig, orbit_ctl, app, opts, i).await?;
+        let pending = with_renderer(renderer_sig, |r| r.capture(&opts.frame, view, proj))?;
+        let png = capture::encode_png(&pending.finish().await?)?;
+        zip.add(&format!("frame_{i:05}.png"), &png)
+            .map_err(|e| anyhow!(e))?;
+    }
+    let name = format!(
+        "em_viz_{}x{}_{}fps.zip",
+        opts.frame.width, opts.frame.height, opts.fps
+    );
+    let mib = zip.len() as f64 / (1024.0 * 1024.0);
+    download::save_bytes(&name, "application/zip", &zip.finish())?;
+    Ok(format!("saved {name} ({n} frames, {mib:.0} MiB)"))
+}
+
+async fn record_webm(
+    renderer_sig: RendererSignal,
+    orbit_ctl: &Rc<RefCell<OrbitController>>,
+    canvas: &HtmlCanvasElement,
+    app: AppState,
+    opts: &RecordOptions,
+) -> Result<String> {
+    let (w, h) = (opts.frame.width, opts.frame.height);
+    with_renderer(renderer_sig, |r| {
+        r.resize(w, h);
+        Ok(())
+    })?;
+    let recorder = WebmRecorder::start(canvas, opts.fps)?;
+    let n = opts.frame_count();
+    let start_ms = now_ms();
+    for i in 0..n {
+        let (view, proj) = step(renderer_sig, orbit_ctl, app, opts, i).await?;
+        with_renderer(renderer_sig, |r| {
+            r.update_viewproj(view, proj);
+            r.render()
+        })?;
+        recorder.request_frame();
+        // MediaRecorder stamps frames with wall-clock time, so hold each one
+        // for its slot; slower frames stretch the video instead of dropping
+        while now_ms() < start_ms + (i + 1) as f64 * 1000.0 / opts.fps as f64 {
+            next_frame().await;
+        }
+    }
+    let blob = recorder.stop().await?;
+    let name = format!("em_viz_{w}x{h}_{}fps.webm", opts.fps);
+    download::save_blob(&name, &blob)?;
+    Ok(format!("saved {name} ({n} frames)"))
+}
+
+/// Advance to frame `i` (frame 0 is the current state), recompute the ribbons
+/// for it and return the frame's view / projection.
+async fn step(
+    renderer_sig: RendererSignal,
+    orbit_ctl: &Rc<RefCell<OrbitController>>,
+    app: AppState,
+    opts: &RecordOptions,
+    i: u32,
+) -> Result<([f32; 16], [f32; 16])> {
+    if !app.recording.get_untracked() {
+        bail!("cancelled at frame {}", i + 1);
+    }
+    app.record_status
+        .set(Some(format!("frame {}/{}", i + 1, opts.frame_count())));
+    if i > 0 {
+        let motion = app.motion.get_untracked();
+        let dt = 1.0 / opts.fps as f32;
+        app.charges.update(|c| motion.advance_charges(c, dt));
+        let mut ctl = orbit_ctl.borrow_mut();
+        let orbit = motion.advance_orbit(ctl.orbit(), dt);
+        ctl.set_orbit(orbit);
+    }
+
+    let scene = app.scene();
+    let source = app.field_source.get_untracked();
+    wait_for_ribbons(renderer_sig).await?;
+    with_renderer(renderer_sig, |r| {
+        r.update_charges(&scene.charges, &app.selection.get_untracked());
+        r.set_b_lines(app.show_b.get_untracked().then_some(&scene));
+        r.start_compute_ribbons_e(&scene.charges, &scene.e_seeds(), &scene.integrator, source)
+    })?;
+    wait_for_ribbons(renderer_sig).await?;
+
+    let mut cam = Camera::new(opts.frame.width as f32 / opts.frame.height.max(1) as f32);
+    cam.update_from_orbit(&orbit_ctl.borrow().orbit());
+    Ok((cam.view().to_cols_array(), cam.proj().to_cols_array()))
+}
+
+fn with_renderer<T>(
+    renderer_sig: RendererSignal,
+    f: impl FnOnce(&mut Renderer) -> Result<T>,
+) -> Result<T> {
+    renderer_sig
+        .try_update_untracked(|opt| opt.as_mut().map(f))
+        .flatten()
+        .context("renderer went away (device lost?)")?
+}
+
+async fn wait_for_ribbons(renderer_sig: RendererSignal) -> Result<()> {
+    while with_renderer(renderer_sig, |r| Ok(r.ribbons_pending()))? {
+        next_frame().await;
+    }
+    Ok(())
+}
+
+/// Resolves on the next animation frame, which also lets GPU callbacks run.
+pub async fn next_frame() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = web_sys::window()
+            .expect("window")
+            .request_animation_frame(&resolve);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+fn now_ms() -> f64 {
+    web_sys::window()
+        .and_then(|w| w.performance())
+        .map_or(0.0, |p| p.now())
+}
+
+/// MediaRecorder on a manually clocked canvas stream: a frame is only taken
+/// when `request_frame` is called.
+struct WebmRecorder {
+    recorder: MediaRecorder,
+    track: CanvasCaptureMediaStreamTrack,
+    chunks: Rc<RefCell<Vec<Blob>>>,
+    mime: &'static str,
+    _on_data: Closure<dyn FnMut(BlobEvent)>,
+}
+
+impl WebmRecorder {
+    fn start(canvas: &HtmlCanvasElement, fps: u32) -> Result<Self> {
+        let js = |e: wasm_bindgen::JsValue| anyhow!("{e:?}");
+        let mime = ["video/webm;codecs=vp9", "video/webm"]
+            .into_iter()
+            .find(|m| MediaRecorder::is_type_supported(m))
+            .context("this browser can't record WebM")?;
+        let stream = canvas
+            .capture_stream_with_frame_request_rate(0.0)
+            .map_err(js)?;
+        let track: CanvasCaptureMediaStreamTrack = stream
+            .get_video_tracks()
+            .get(0)
+            .dyn_into()
+            .map_err(|_| anyhow!("canvas stream has no video track"))?;
+
+        let options = MediaRecorderOptions::new();
+        options.set_mime_type(mime);
+        // ~0.1 bit per pixel per frame keeps fine ribbons legible
+        let bits = canvas.width() as f64 * canvas.height() as f64 * fps as f64 * 0.1;
+        options.set_video_bits_per_second(bits.clamp(2e6, 1e8) as u32);
+        let recorder =
+            MediaRecorder::new_with_media_stream_and_media_recorder_options(&stream, &options)
+                .map_err(js)?;
+
+        let chunks = Rc::new(RefCell::new(Vec::new()));
+        let sink = chunks.clone();
+        let on_data = Closure::<dyn FnMut(BlobEvent)>::new(move |ev: BlobEvent| {
+            if let Some(blob) = ev.data() {
+                sink.borrow_mut().push(blob);
+            }
+        });
+        recorder.set_ondataavailable(Some(on_data.as_ref().unchecked_ref()));
+        recorder.start().map_err(js)?;
+        Ok(Self {
+            recorder,
+            track,
+            chunks,
+            mime,
+            _on_data: on_data,
+        })
+    }
+
+    fn request_frame(&self) {
+        self.track.request_frame();
+    }
+
+    async fn stop(self) -> Result<Blob> {
+        let (tx, rx) = oneshot::channel();
+        let on_stop = Closure::once(move || {
+            let _ = tx.send(());
+        });
+        self.recorder
+            .set_onstop(Some(on_stop.as_ref().unchecked_ref()));
+        self.recorder.stop().map_err(|e| anyhow!("{e:?}"))?;
+        rx.await.context("recorder dropped")?;
+
+        let parts = js_sys::Array::new();
+        for blob in self.chunks.borrow().iter() {
+            parts.push(blob);
+        }
+        let opts = BlobPropertyBag::new();
+        opts.set_type(self.mime);
+        Blob::new_with_blob_sequence_and_options(&parts, &opts).map_err(|e| anyhow!("{e:?}"))
+    }
+}
+
+impl Drop for WebmRecorder {
+    // A recording abandoned mid-way (cancel, failed frame) is still running;
+    // detach the handlers before their closures go and stop it.
+    fn drop(&mut self) {
+        self.recorder.set_ondataavailable(None);
+        self.recorder.set_onstop(None);
+        if self.recorder.state() != RecordingState::Inactive {
+            let _ = self.recorder.stop();
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Backend selection: WebGPU when the browser has it, WebGL2 otherwise.
+
+use crate::capture::{CaptureOptions, PendingCapture};
+use crate::field_grid::FieldSource;
+use crate::gpu_errors::ErrorSink;
+use crate::perf::Scope;
+use crate::webgl_renderer::WebGlRenderer;
+use crate::wgpu_renderer::WgpuRenderer;
+use em_viz_core::colormap::Colormaps;
+use em_viz_core::em3d::Charge3D;
+use em_viz_core::scene::{Integrator, Scene};
+use em_viz_core::trace::{self, Ribbons};
+use leptos::logging::log;
+use web_sys::HtmlCanvasElement;
+
+pub enum Renderer {
+    // boxed: both hold a lot of GPU state and the signal only stores the enum
+    WebGpu(Box<WgpuRenderer>),
+    WebGl2(Box<WebGlRenderer>),
+}
+
+impl Renderer {
+    /// Picks the backend once at startup. Probing happens before any context is
+    /// created on `canvas`, since a canvas can only ever hand out one kind.
+    /// WebGPU errors and device loss are reported through `errors`.
+    pub async fn new(
+        canvas: HtmlCanvasElement,
+        initial_charges: &[Charge3D],
+        point_px: f32,
+        errors: ErrorSink,
+    ) -> anyhow::Result<Self> {
+        if wgpu::util::is_browser_webgpu_supported().await {
+            WgpuRenderer::new(canvas, initial_charges, point_px, errors)
+                .await
+                .map(|r| Self::WebGpu(Box::new(r)))
+        } else {
+            log!("WebGPU unavailable, falling back to WebGL2 with CPU tracing");
+            WebGlRenderer::new(canvas, initial_charges, point_px).map(|r| Self::WebGl2(Box::new(r)))
+        }
+    }
+
+    /// Short backend name for the HUD.
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            Self::WebGpu(_) => "WebGPU",
+            Self::WebGl2(_) => "WebGL2 (CPU trace)",
+        }
+    }
+
+    /// Where the HUD's pass timings come from.
+    pub fn timing_source(&self) -> &'static str {
+        match self {
+            Self::WebGpu(r) => r.timing_source().label(),
+            Self::WebGl2(_) => "CPU scopes",
+        }
+    }
+
+    pub fn update_viewproj(&mut self, view: [f32; 16], proj: [f32; 16]) {
+        match self {
+            Self::WebGpu(r) => r.update_viewproj(view, proj),
+            Self::WebGl2(r) => r.update_viewproj(view, proj),
+        }
+    }
+
+    pub fn set_point_size(&mut self, px: f32) {
+        match self {
+            Self::WebGpu(r) => r.set_point_size(px),
+            Self::WebGl2(r) => r.set_point_size(px),
+        }
+    }
+
+    pub fn update_charges(&mut self, charges: &[Charge3D], selected: &[usize]) {
+        match self {
+            Self::WebGpu(r) => r.update_charges(charges, selected),
+            Self::WebGl2(r) => r.update_charges(charges, selected),
+        }
+    }
+
+    pub fn set_pool_budget(&mut self, bytes: u64) {
+        match self {
+            Self::WebGpu(r) => r.set_pool_budget(bytes),
+            Self::WebGl2(r) => r.set_pool_budget(bytes),
+        }
+    }
+
+    pub fn start_compute_ribbons_e(
+        &mut self,
+        charges: &[Charge3D],
+        seeds: &[[f32; 4]],
+        integrator: &Integrator,
+        source: FieldSource,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::WebGpu(r) => r.start_compute_ribbons_e(charges, seeds, integrator, source),
+            Self::WebGl2(r) => r.start_compute_ribbons_e(charges, seeds, integrator, source),
+        }
+    }
+
+    /// Trace the B lines of `scene` (rings around its moving charges) on the
+    /// CPU and show them; `None` hides the layer. Both backends draw the same
+    /// vertices, there is no B compute shader.
+    pub fn set_b_lines(&mut self, scene: Option<&Scene>) {
+        let ribbons = match scene {
+            Some(scene) => {
+                let it = &scene.integrator;
+                let seeds = scene.b_seeds();
+                let _t = Scope::new(format!("b trace n={}", seeds.len()));
+                trace::trace_b_ribbons(
+                    &scene.charges,
+                    &seeds,
+                    it.h,
+                    it.max_pts,
+                    it.k,
+                    it.soft2,
+                    it.c_inv2,
+                )
+            }
+            None => Ribbons::default(),
+        };
+        match self {
+            Self::WebGpu(r) => r.set_ribbons_b(&ribbons),
+            Self::WebGl2(r) => r.set_ribbons_b(&ribbons),
+        }
+    }
+
+    pub fn set_colormaps(&mut self, colormaps: Colormaps) {
+        match self {
+            Self::WebGpu(r) => r.set_colormaps(colormaps),
+            Self::WebGl2(r) => r.set_colormaps(colormaps),
+        }
+    }
+
+    pub fn render(&mut self) -> anyhow::Result<()> {
+        match self {
+            Self::WebGpu(r) => r.render(),
+            Self::WebGl2(r) => r.render(),
+        }
+    }
+
+    /// Whether submitted ribbons are still being computed. The WebGL2 path
+    /// traces synchronously, so it never is.
+    pub fn ribbons_pending(&mut self) -> bool {
+        match self {
+            Self::WebGpu(r) => r.ribbons_pending(),
+            Self::WebGl2(_) => false,
+        }
+    }
+
+    /// Offscreen high-resolution capture; WebGPU only.
+    pub fn capture(
+        &mut self,
+        opts: &CaptureOptions,
+        view: [f32; 16],
+        proj: [f32; 16],
+    ) -> anyhow::Result<PendingCapture> {
+        match self {
+            Self::WebGpu(r) => r.capture(opts, view, proj),
+            Self::WebGl2(_) => anyhow::bail!("high-resolution capture needs WebGPU"),
+        }
+    }
+
+    pub fn resize(&mut self, w: u32, h: u32) {
+        match self {
+            Self::WebGpu(r) => r.resize(w, h),
+            Self::WebGl2(r) => r.resize(w, h),
+        }
+    }
+
+    pub fn clear_ribbons(&mut self) {
+        match self {
+            Self::WebGpu(r) => r.clear_ribbons(),
+            Self::WebGl2(r) => r.clear_ribbons(),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Saving and loading scene files (see `em_viz_core::scene_file`) from the
+//! sidebar. The CLI reads the same files.
+
+use crate::download;
+use crate::history;
+use crate::state::AppState;
+use anyhow::{Result, anyhow};
+use em_viz_core::scene_file::{SceneFile, Syntax};
+use wasm_bindgen_futures::JsFuture;
+
+/// Download the current scene, view settings and camera.
+pub fn save(app: &AppState, syntax: Syntax) -> Result<()> {
+    let (name, mime) = match syntax {
+        Syntax::Json => ("em_viz_scene.json", "application/json"),
+        Syntax::Ron => ("em_viz_scene.ron", "text/plain"),
+    };
+    download::save_text(name, mime, &app.scene_file().to_text(syntax))
+}
+
+/// Read `file` (JSON or RON by extension, any version) and open it in `app`
+/// as an undoable step.
+pub async fn load(app: AppState, file: web_sys::File) -> Result<()> {
+    let text = JsFuture::from(file.text())
+        .await
+        .map_err(|e| anyhow!("reading {}: {e:?}", file.name()))?
+        .as_string()
+        .unwrap_or_default();
+    let scene = SceneFile::parse(&text, Syntax::from_name(&file.name()))
+        .map_err(|e| anyhow!("{}: {e}", file.name()))?;
+    history::open(&app, scene);
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! Scene links: the whole scene file lives in the URL fragment
+//! (`#scene=<payload>`, see `em_viz_core::share`). The fragment is read at
+//! startup and on `hashchange`, and rewritten shortly after the scene changes,
+//! so the address bar always holds a link to what is on screen.
+
+use crate::history;
+use crate::state::AppState;
+use em_viz_core::scene_file::SceneFile;
+use em_viz_core::share;
+use leptos::prelude::*;
+use leptos_use::{DebounceOptions, use_debounce_fn_with_options};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+const KEY: &str = "scene=";
+
+/// Load the scene in the current fragment, if there is one, and keep the
+/// fragment in sync from then on. A link the page starts with replaces the
+/// session; one pasted later is opened as an undoable step. Links that fail to
+/// load are reported in `error`. Returns whether a scene was loaded at startup.
+pub fn attach(app: AppState, error: RwSignal<Option<String>>) -> bool {
+    let load = move |open: fn(&AppState, SceneFile)| match load_fragment(app, open) {
+        Some(Err(e)) => {
+            error.set(Some(format!("scene link: {e}")));
+            false
+        }
+        Some(Ok(())) => {
+            error.set(None);
+            true
+        }
+        None => false,
+    };
+    let loaded = load(AppState::load_file);
+    let on_hash = Closure::<dyn FnMut()>::new(move || {
+        load(history::open);
+    });
+    let _ =
+        window().add_event_listener_with_callback("hashchange", on_hash.as_ref().unchecked_ref());
+    on_hash.forget();
+
+    // replaceState: edits shouldn't pile up in the back button, and it doesn't
+    // fire `hashchange`
+    let write = use_debounce_fn_with_options(
+        move || {
+            let url = format!("#{KEY}{}", share::encode(&app.scene_file()));
+            if window().location().hash().ok().as_deref() != Some(url.as_str())
+                && let Ok(history) = window().history()
+            {
+                let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&url));
+            }
+        },
+        400.0,
+        DebounceOptions::default().max_wait(Some(2000.0)),
+    );
+    Effect::new(move |_| {
+        app.track_scene();
+        write();
+    });
+    loaded
+}
+
+/// `None` when the fragment holds no scene.
+fn load_fragment(app: AppState, open: fn(&AppState, SceneFile)) -> Option<Result<(), String>> {
+    let hash = window().location().hash().ok()?;
+    let payload = hash.strip_prefix('#')?.strip_prefix(KEY)?;
+    Some(share::decode(payload).map(|file| open(&app, file)))
+}
@@ -1,29 +1,107 @@
-use crate::em3d::Charge3D;
+use crate::capture::CaptureOptions;
+use crate::field_grid::FieldSource;
+use crate::history::{self, Edit};
+use crate::record::RecordOptions;
+use em_viz_core::anim::Motion;
+use em_viz_core::camera::Orbit;
+use em_viz_core::colormap::Colormaps;
+use em_viz_core::em3d::{Charge3D, FieldEval};
+use em_viz_core::gizmo::Handle;
+use em_viz_core::group::GroupOp;
+use em_viz_core::history::{ChargeChange, ChargeEdit, History};
+use em_viz_core::presets::PresetScene;
+use em_viz_core::scene::{Integrator, Scene, Seeding};
+use em_viz_core::scene_file::{Display, Layers, SceneFile};
 use glam::{Mat4, Vec3};
 use leptos::prelude::*;
 
 #[derive(Clone, Copy, Default)]
 pub struct Drag3D {
     pub active: bool,
-    pub idx: usize,
+    // gizmo handle constraining the move; None drags on the plane below
+    pub handle: Option<Handle>,
+    // charge whose velocity tip is dragged (on the plane below) instead
+    pub velocity: Option<usize>,
     pub plane_p: Vec3,
     pub plane_n: Vec3,
-    pub hit_offset: Vec3,
+    // selection centroid and the constrained point under the pointer when the
+    // drag started
+    pub origin: Vec3,
+    pub grab: Vec3,
+    // bumped per drag so its moves make one undo step
+    pub gesture: u32,
+}
+
+/// Box select in progress, in CSS px from the canvas' top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Marquee {
+    pub start: (f32, f32),
+    pub end: (f32, f32),
+}
+
+impl Marquee {
+    /// Left, top, width, height.
+    pub fn rect(&self) -> (f32, f32, f32, f32) {
+        let (x0, y0) = self.start;
+        let (x1, y1) = self.end;
+        (x0.min(x1), y0.min(y1), (x1 - x0).abs(), (y1 - y0).abs())
+    }
 }
 
 #[derive(Clone, Copy)]
 pub struct AppState {
     pub charges: RwSignal<Vec<Charge3D>>,
     pub drag: RwSignal<Drag3D>,
+    // selected charge indices, sorted; highlighted on the canvas
+    pub selection: RwSignal<Vec<usize>>,
+    pub marquee: RwSignal<Option<Marquee>>,
+    // grid step drags snap to; None for free placement
+    pub snap: RwSignal<Option<f32>>,
+    // scene edits, see `crate::history`
+    pub history: RwSignal<History<Edit>>,
 
     // realtime camera bits used by picking/render; updated EVERY FRAME
     pub eye_rt: RwSignal<Vec3>,
-    pub inv_vp: RwSignal<Mat4>, // cached inverse(proj*view)
+    pub inv_vp: RwSignal<Mat4>,            // cached inverse(proj*view)
+    pub viewport_rt: RwSignal<(u32, u32)>, // canvas size in device pixels
+    pub orbit_rt: RwSignal<Orbit>,
+    // camera to jump to on the next frame (scene loads)
+    pub orbit_request: RwSignal<Option<Orbit>>,
 
     // controls
     pub seeds_per_charge_e: RwSignal<String>,
     pub show_e: RwSignal<bool>,
+    pub show_b: RwSignal<bool>,
+    pub colormaps: RwSignal<Colormaps>,
+    pub field_eval: RwSignal<FieldEval>,
+    // seeding / integrator settings without a control of their own (shell
+    // radius, step length, ...); the fields above override their counterparts
+    pub seeding: RwSignal<Seeding>,
+    pub integrator: RwSignal<Integrator>,
+    pub field_source: RwSignal<FieldSource>,
     pub point_size_px: RwSignal<f32>,
+    pub pool_budget_mib: RwSignal<f32>,
+
+    // last ribbon compute failure (e.g. pool budget exceeded); None when fine
+    pub compute_error: RwSignal<Option<String>>,
+    // validation / OOM / device-lost messages from the GPU, oldest first
+    pub gpu_errors: RwSignal<Vec<String>>,
+
+    // PNG capture settings; the RAF loop takes `capture_request` on its next
+    // frame and reports progress / errors in `capture_status`
+    pub capture_opts: RwSignal<CaptureOptions>,
+    pub capture_request: RwSignal<Option<CaptureOptions>>,
+    pub capture_status: RwSignal<Option<String>>,
+
+    // frame-sequence export; while `recording` is set the RAF loop leaves the
+    // renderer to the recorder, clearing it cancels
+    pub record_opts: RwSignal<RecordOptions>,
+    pub record_request: RwSignal<Option<RecordOptions>>,
+    pub recording: RwSignal<bool>,
+    pub record_status: RwSignal<Option<String>>,
+
+    // charge drift / camera orbit, stepped per frame
+    pub motion: RwSignal<Motion>,
 
     // pause / play RAF-driven simulation & rendering
     pub paused: RwSignal<bool>,
@@ -35,15 +113,222 @@ impl AppState {
         Self {
             charges: RwSignal::new(initial_charges),
             drag: RwSignal::new(Drag3D::default()),
+            selection: RwSignal::new(vec![]),
+            marquee: RwSignal::new(None),
+            snap: RwSignal::new(None),
+            history: RwSignal::new(History::new(history::LIMIT)),
             eye_rt: RwSignal::new(Vec3::ZERO),
             inv_vp: RwSignal::new(Mat4::IDENTITY),
+            viewport_rt: RwSignal::new((1, 1)),
+            orbit_rt: RwSignal::new(crate::camera::OrbitController::new().orbit()),
+            orbit_request: RwSignal::new(None),
 
             seeds_per_charge_e: RwSignal::new("30".into()),
             show_e: RwSignal::new(true),
+            show_b: RwSignal::new(true),
+            colormaps: RwSignal::new(Colormaps::default()),
+            field_eval: RwSignal::new(FieldEval::Direct),
+            seeding: RwSignal::new(Seeding::default()),
+            integrator: RwSignal::new(Integrator::default()),
+            field_source: RwSignal::new(FieldSource::Exact),
             point_size_px: RwSignal::new(point_size_default),
+            pool_budget_mib: RwSignal::new(256.0),
+
+            compute_error: RwSignal::new(None),
+            gpu_errors: RwSignal::new(vec![]),
+
+            capture_opts: RwSignal::new(CaptureOptions::default()),
+            capture_request: RwSignal::new(None),
+            capture_status: RwSignal::new(None),
+
+            record_opts: RwSignal::new(RecordOptions::default()),
+            record_request: RwSignal::new(None),
+            recording: RwSignal::new(false),
+            record_status: RwSignal::new(None),
+
+            motion: RwSignal::new(Motion::default()),
 
             paused: RwSignal::new(false),
             hud_text: RwSignal::new("".into()),
         }
     }
+
+    /// Snapshot of the current charges and settings as a core [`Scene`], for
+    /// CPU-side exports.
+    pub fn scene(&self) -> Scene {
+        Scene {
+            charges: self.charges.get_untracked(),
+            seeding: Seeding {
+                e_per_charge: self
+                    .seeds_per_charge_e
+                    .get_untracked()
+                    .parse()
+                    .unwrap_or(30),
+                ..self.seeding.get_untracked()
+            },
+            integrator: Integrator {
+                eval: self.field_eval.get_untracked(),
+                ..self.integrator.get_untracked()
+            },
+        }
+    }
+
+    /// Everything a saved scene file holds, including the current camera.
+    pub fn scene_file(&self) -> SceneFile {
+        SceneFile {
+            layers: Layers {
+                e: self.show_e.get_untracked(),
+                b: self.show_b.get_untracked(),
+            },
+            display: Display {
+                point_size_px: self.point_size_px.get_untracked(),
+                colormaps: self.colormaps.get_untracked(),
+                grid_res: self.field_source.get_untracked().grid_res(),
+            },
+            camera: Some(self.orbit_rt.get_untracked()),
+            motion: self.motion.get_untracked(),
+            ..SceneFile::new(self.scene())
+        }
+    }
+
+    /// Subscribe the running effect to everything [`Self::scene_file`] reads.
+    pub fn track_scene(&self) {
+        self.charges.track();
+        self.seeds_per_charge_e.track();
+        self.field_eval.track();
+        self.seeding.track();
+        self.integrator.track();
+        self.show_e.track();
+        self.show_b.track();
+        self.point_size_px.track();
+        self.colormaps.track();
+        self.field_source.track();
+        self.motion.track();
+        self.orbit_rt.track();
+    }
+
+    /// The scene file minus charges and camera: what settings edits change.
+    pub fn settings(&self) -> SceneFile {
+        SceneFile {
+            charges: vec![],
+            camera: None,
+            ..self.scene_file()
+        }
+    }
+
+    /// Set everything in `file` except charges and camera.
+    pub fn set_settings(&self, file: &SceneFile) {
+        self.seeds_per_charge_e
+            .set(file.seeding.e_per_charge.to_string());
+        self.field_eval.set(file.integrator.eval);
+        self.seeding.set(file.seeding.clone());
+        self.integrator.set(file.integrator.clone());
+        self.show_e.set(file.layers.e);
+        self.show_b.set(file.layers.b);
+        self.point_size_px.set(file.display.point_size_px);
+        self.colormaps.set(file.display.colormaps);
+        self.field_source
+            .set(FieldSource::from_grid_res(file.display.grid_res));
+        self.motion.set(file.motion);
+    }
+
+    /// Replace the scene and view settings with those of `file`. The camera
+    /// moves on the next frame.
+    pub fn apply_file(&self, file: &SceneFile) {
+        self.set_settings(file);
+        self.selection.set(vec![]);
+        if let Some(orbit) = file.camera {
+            self.orbit_rt.set(orbit);
+            self.orbit_request.set(Some(orbit));
+        }
+        self.charges.set(file.charges.clone());
+    }
+
+    /// Start over from `file` (links, restored sessions): applies it and
+    /// forgets the undo history.
+    pub fn load_file(&self, file: SceneFile) {
+        self.apply_file(&file);
+        self.history.set(History::new(history::LIMIT));
+    }
+
+    /// Select charge `idx` alone, or with `toggle` add / remove it.
+    pub fn select(&self, idx: usize, toggle: bool) {
+        self.selection.update(|sel| match sel.binary_search(&idx) {
+            Ok(at) if toggle => {
+                sel.remove(at);
+            }
+            Err(at) if toggle => sel.insert(at, idx),
+            _ => *sel = vec![idx],
+        });
+    }
+
+    /// Append `charge` and select it. Undoable, like the edits below.
+    pub fn add_charge(&self, charge: Charge3D) {
+        let idx = self.charges.with_untracked(Vec::len);
+        history::edit_charges(self, ChargeEdit::Insert { idx, charge }, None);
+        self.selection.set(vec![idx]);
+    }
+
+    pub fn set_charge(&self, idx: usize, charge: Charge3D) {
+        let Some(before) = self.charges.with_untracked(|cs| cs.get(idx).copied()) else {
+            return;
+        };
+        if before != charge {
+            let change = ChargeChange {
+                idx,
+                before,
+                after: charge,
+            };
+            history::edit_charges(self, ChargeEdit::Update(vec![change]), None);
+        }
+    }
+
+    pub fn delete_charge(&self, idx: usize) {
+        let Some(charge) = self.charges.with_untracked(|cs| cs.get(idx).copied()) else {
+            return;
+        };
+        history::edit_charges(self, ChargeEdit::Remove { idx, charge }, None);
+    }
+
+    pub fn delete_selection(&self) {
+        let sel = self.selection.get_untracked();
+        match sel.as_slice() {
+            [] => {}
+            [idx] => self.delete_charge(*idx),
+            _ => {
+                let before = self.charges.get_untracked();
+                let after = before
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| sel.binary_search(i).is_err())
+                    .map(|(_, c)| *c)
+                    .collect();
+                history::edit_charges(self, ChargeEdit::Replace { before, after }, None);
+            }
+        }
+    }
+
+    /// Apply `op` to the selection as one undo step.
+    pub fn transform_selection(&self, op: GroupOp) {
+        let changes = self
+            .selection
+            .with_untracked(|sel| self.charges.with_untracked(|cs| op.changes(cs, sel)));
+        if !changes.is_empty() {
+            history::edit_charges(self, ChargeEdit::Update(changes), None);
+        }
+    }
+
+    /// Swap in a preset's charges, seeding and camera; the other settings stay.
+    /// Undoable.
+    pub fn load_preset(&self, preset: PresetScene) {
+        history::open(
+            self,
+            SceneFile {
+                charges: preset.charges,
+                seeding: preset.seeding,
+                camera: Some(preset.camera),
+                ..self.scene_file()
+            },
+        );
+    }
 }
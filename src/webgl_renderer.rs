@@ -0,0 +1,388 @@
+//! WebGL2 fallback for browsers without WebGPU. Ribbons are traced on the CPU
+//! (`em_viz_core::trace`) and drawn with the GLSL ribbon / sphere-impostor shaders.
+
+use crate::field_grid::FieldSource;
+use crate::perf::Scope;
+use em_viz_core::colormap::{Colormap, Colormaps};
+use em_viz_core::em3d::Charge3D;
+use em_viz_core::scene::Integrator;
+use em_viz_core::trace::{self, RibbonVertex, Ribbons};
+use wasm_bindgen::JsCast;
+use web_sys::{
+    HtmlCanvasElement, WebGl2RenderingContext as Gl, WebGlBuffer, WebGlProgram,
+    WebGlUniformLocation, WebGlVertexArrayObject,
+};
+
+const RIBBON_VERT: &str = include_str!("../static/shaders/ribbon3d.vert.glsl");
+const RIBBON_FRAG: &str = include_str!("../static/shaders/ribbon3d.frag.glsl");
+const SPHERE_VERT: &str = include_str!("../static/shaders/sphere_impostor.vert.glsl");
+const SPHERE_FRAG: &str = include_str!("../static/shaders/sphere_impostor.frag.glsl");
+
+const RIBBON_STRIDE: i32 = std::mem::size_of::<RibbonVertex>() as i32;
+
+fn js_err(e: wasm_bindgen::JsValue) -> anyhow::Error {
+    anyhow::anyhow!("{e:?}")
+}
+
+fn compile(gl: &Gl, kind: u32, src: &str) -> anyhow::Result<web_sys::WebGlShader> {
+    let shader = gl
+        .create_shader(kind)
+        .ok_or_else(|| anyhow::anyhow!("create_shader failed"))?;
+    gl.shader_source(&shader, src);
+    gl.compile_shader(&shader);
+    if gl
+        .get_shader_parameter(&shader, Gl::COMPILE_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(shader)
+    } else {
+        let log = gl.get_shader_info_log(&shader).unwrap_or_default();
+        anyhow::bail!("GLSL compile error: {log}")
+    }
+}
+
+fn link(gl: &Gl, vert: &str, frag: &str) -> anyhow::Result<WebGlProgram> {
+    let vs = compile(gl, Gl::VERTEX_SHADER, vert)?;
+    let fs = compile(gl, Gl::FRAGMENT_SHADER, frag)?;
+    let prog = gl
+        .create_program()
+        .ok_or_else(|| anyhow::anyhow!("create_program failed"))?;
+    gl.attach_shader(&prog, &vs);
+    gl.attach_shader(&prog, &fs);
+    gl.link_program(&prog);
+    if gl
+        .get_program_parameter(&prog, Gl::LINK_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(prog)
+    } else {
+        let log = gl.get_program_info_log(&prog).unwrap_or_default();
+        anyhow::bail!("GLSL link error: {log}")
+    }
+}
+
+fn vertex_array(gl: &Gl) -> anyhow::Result<(WebGlVertexArrayObject, WebGlBuffer)> {
+    let vao = gl
+        .create_vertex_array()
+        .ok_or_else(|| anyhow::anyhow!("create_vertex_array failed"))?;
+    let vbo = gl
+        .create_buffer()
+        .ok_or_else(|| anyhow::anyhow!("create_buffer failed"))?;
+    Ok((vao, vbo))
+}
+
+/// Inputs of the last trace; ribbons are only re-traced when these change.
+#[derive(PartialEq)]
+struct TraceKey {
+    charges: Vec<Charge3D>,
+    seeds: Vec<[f32; 4]>,
+    integrator: Integrator,
+}
+
+struct RibbonLayer {
+    prog: WebGlProgram,
+    vao: WebGlVertexArrayObject,
+    vbo: WebGlBuffer,
+    u_view: Option<WebGlUniformLocation>,
+    u_proj: Option<WebGlUniformLocation>,
+    u_viewport: Option<WebGlUniformLocation>,
+    u_half_width: Option<WebGlUniformLocation>,
+    u_alpha: Option<WebGlUniformLocation>,
+    u_palette: Option<WebGlUniformLocation>,
+    palette: Colormap,
+    strips: Vec<(u32, u32)>,
+}
+
+impl RibbonLayer {
+    fn new(gl: &Gl, palette: Colormap) -> anyhow::Result<Self> {
+        let prog = link(gl, RIBBON_VERT, RIBBON_FRAG)?;
+        let (vao, vbo) = vertex_array(gl)?;
+        gl.bind_vertex_array(Some(&vao));
+        gl.bind_buffer(Gl::ARRAY_BUFFER, Some(&vbo));
+        // (center, side) (tangent, tone), same packing as the compute output
+        for (loc, size, offset) in [(0, 3, 0), (2, 1, 12), (1, 3, 16), (3, 1, 28)] {
+            gl.enable_vertex_attrib_array(loc);
+            gl.vertex_attrib_pointer_with_i32(loc, size, Gl::FLOAT, false, RIBBON_STRIDE, offset);
+        }
+        gl.bind_vertex_array(None);
+
+        let u = |name| gl.get_uniform_location(&prog, name);
+        Ok(Self {
+            u_view: u("u_view"),
+            u_proj: u("u_proj"),
+            u_viewport: u("u_viewport"),
+            u_half_width: u("u_halfWidthPx"),
+            u_alpha: u("u_alpha"),
+            u_palette: u("u_palette"),
+            palette,
+            prog,
+            vao,
+            vbo,
+            strips: vec![],
+        })
+    }
+
+    fn upload(&mut self, gl: &Gl, ribbons: &Ribbons) {
+        gl.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.vbo));
+        gl.buffer_data_with_u8_array(
+            Gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(&ribbons.verts),
+            Gl::DYNAMIC_DRAW,
+        );
+        self.strips = ribbons.strips.clone();
+    }
+
+    fn draw(&self, gl: &Gl, viewport: [f32; 2], view: &[f32; 16], proj: &[f32; 16]) {
+        if self.strips.is_empty() {
+            return;
+        }
+        gl.use_program(Some(&self.prog));
+        gl.uniform_matrix4fv_with_f32_array(self.u_view.as_ref(), false, view);
+        gl.uniform_matrix4fv_with_f32_array(self.u_proj.as_ref(), false, proj);
+        gl.uniform2f(self.u_viewport.as_ref(), viewport[0], viewport[1]);
+        // same thickness/alpha as the WebGPU ribbons
+        gl.uniform1f(self.u_half_width.as_ref(), 2.0);
+        gl.uniform1f(self.u_alpha.as_ref(), 0.85);
+        gl.uniform1i(self.u_palette.as_ref(), self.palette.index() as i32);
+        gl.bind_vertex_array(Some(&self.vao));
+        for &(first, count) in &self.strips {
+            if count > 0 {
+                gl.draw_arrays(Gl::TRIANGLE_STRIP, first as i32, count as i32);
+            }
+        }
+        gl.bind_vertex_array(None);
+    }
+}
+
+struct SphereLayer {
+    prog: WebGlProgram,
+    vao: WebGlVertexArrayObject,
+    vbo: WebGlBuffer,
+    u_view: Option<WebGlUniformLocation>,
+    u_proj: Option<WebGlUniformLocation>,
+    u_point_size: Option<WebGlUniformLocation>,
+    count: i32,
+}
+
+impl SphereLayer {
+    fn new(gl: &Gl) -> anyhow::Result<Self> {
+        let prog = link(gl, SPHERE_VERT, SPHERE_FRAG)?;
+        let (vao, vbo) = vertex_array(gl)?;
+        gl.bind_vertex_array(Some(&vao));
+        gl.bind_buffer(Gl::ARRAY_BUFFER, Some(&vbo));
+        // centre xyz, selected flag
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer_with_i32(0, 3, Gl::FLOAT, false, 16, 0);
+        gl.enable_vertex_attrib_array(1);
+        gl.vertex_attrib_pointer_with_i32(1, 1, Gl::FLOAT, false, 16, 12);
+        gl.bind_vertex_array(None);
+
+        let u = |name| gl.get_uniform_location(&prog, name);
+        Ok(Self {
+            u_view: u("u_view"),
+            u_proj: u("u_proj"),
+            u_point_size: u("u_pointSizePx"),
+            prog,
+            vao,
+            vbo,
+            count: 0,
+        })
+    }
+
+    fn upload(&mut self, gl: &Gl, charges: &[Charge3D], selected: &[usize]) {
+        let centers: Vec<[f32; 4]> = charges
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                c.pos
+                    .extend(if selected.binary_search(&i).is_ok() {
+                        1.0
+                    } else {
+                        0.0
+                    })
+                    .to_array()
+            })
+            .collect();
+        gl.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.vbo));
+        gl.buffer_data_with_u8_array(
+            Gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(&centers),
+            Gl::DYNAMIC_DRAW,
+        );
+        self.count = centers.len() as i32;
+    }
+
+    fn draw(&self, gl: &Gl, point_px: f32, view: &[f32; 16], proj: &[f32; 16]) {
+        gl.use_program(Some(&self.prog));
+        gl.uniform_matrix4fv_with_f32_array(self.u_view.as_ref(), false, view);
+        gl.uniform_matrix4fv_with_f32_array(self.u_proj.as_ref(), false, proj);
+        gl.uniform1f(self.u_point_size.as_ref(), point_px);
+        gl.bind_vertex_array(Some(&self.vao));
+        gl.draw_arrays(Gl::POINTS, 0, self.count);
+        gl.bind_vertex_array(None);
+    }
+}
+
+pub struct WebGlRenderer {
+    canvas: HtmlCanvasElement,
+    gl: Gl,
+    size: (u32, u32),
+    viewport: [f32; 2],
+    point_size_px: f32,
+    last_view: [f32; 16],
+    last_proj: [f32; 16],
+    budget_bytes: u64,
+
+    ribbons: RibbonLayer,
+    b_ribbons: RibbonLayer,
+    spheres: SphereLayer,
+    traced: Option<TraceKey>,
+}
+
+impl WebGlRenderer {
+    pub fn new(
+        canvas: HtmlCanvasElement,
+        initial_charges: &[Charge3D],
+        point_px: f32,
+    ) -> anyhow::Result<Self> {
+        let gl: Gl = canvas
+            .get_context("webgl2")
+            .map_err(js_err)?
+            .ok_or_else(|| anyhow::anyhow!("WebGL2 is not available"))?
+            .dyn_into()
+            .map_err(|_| anyhow::anyhow!("webgl2 context has an unexpected type"))?;
+
+        let ribbons = RibbonLayer::new(&gl, Colormaps::default().e)?;
+        let b_ribbons = RibbonLayer::new(&gl, Colormaps::default().b)?;
+        let mut spheres = SphereLayer::new(&gl)?;
+        spheres.upload(&gl, initial_charges, &[]);
+
+        let size = (canvas.width(), canvas.height());
+        Ok(Self {
+            canvas,
+            gl,
+            size,
+            viewport: [size.0 as f32, size.1 as f32],
+            point_size_px: point_px.max(1.0),
+            last_view: [0.0; 16],
+            last_proj: [0.0; 16],
+            budget_bytes: crate::wgpu_renderer::DEFAULT_POOL_BUDGET_BYTES,
+            ribbons,
+            b_ribbons,
+            spheres,
+            traced: None,
+        })
+    }
+
+    pub fn update_viewproj(&mut self, view: [f32; 16], proj: [f32; 16]) {
+        self.last_view = view;
+        self.last_proj = proj;
+    }
+
+    pub fn set_point_size(&mut self, px: f32) {
+        self.point_size_px = px.max(1.0);
+    }
+
+    pub fn update_charges(&mut self, charges: &[Charge3D], selected: &[usize]) {
+        self.spheres.upload(&self.gl, charges, selected);
+    }
+
+    /// Upper bound on the traced vertex data, like the WebGPU pool budget.
+    pub fn set_pool_budget(&mut self, bytes: u64) {
+        self.budget_bytes = bytes;
+    }
+
+    /// CPU counterpart of `WgpuRenderer::start_compute_ribbons_e`. A baked
+    /// `source` is ignored: the fallback always samples the exact field.
+    pub fn start_compute_ribbons_e(
+        &mut self,
+        charges: &[Charge3D],
+        seeds: &[[f32; 4]],
+        integrator: &Integrator,
+        _source: FieldSource,
+    ) -> anyhow::Result<()> {
+        let &Integrator {
+            h,
+            max_pts,
+            k,
+            soft2,
+            eval,
+            ..
+        } = integrator;
+        let key = TraceKey {
+            charges: charges.to_vec(),
+            seeds: seeds.to_vec(),
+            integrator: integrator.clone(),
+        };
+        if self.traced.as_ref() == Some(&key) {
+            return Ok(());
+        }
+
+        let need = seeds.len() as u64 * max_pts as u64 * 2 * RIBBON_STRIDE as u64;
+        if need > self.budget_bytes {
+            anyhow::bail!(
+                "{} ribbons × {} pts need {:.1} MiB of vertex data, budget is {:.1} MiB",
+                seeds.len(),
+                max_pts,
+                need as f64 / (1024.0 * 1024.0),
+                self.budget_bytes as f64 / (1024.0 * 1024.0)
+            );
+        }
+
+        let ribbons = {
+            let _t = Scope::new(format!("cpu trace n={}", seeds.len()));
+            trace::trace_e_ribbons(charges, seeds, h, max_pts, eval, k, soft2)
+        };
+        self.ribbons.upload(&self.gl, &ribbons);
+        self.traced = Some(key);
+        Ok(())
+    }
+
+    pub fn render(&mut self) -> anyhow::Result<()> {
+        let _t = Scope::new("render (webgl2)");
+        let gl = &self.gl;
+        gl.viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
+        gl.clear_color(0.02, 0.02, 0.05, 1.0);
+        gl.clear(Gl::COLOR_BUFFER_BIT);
+        gl.enable(Gl::BLEND);
+        gl.blend_func(Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA);
+
+        self.ribbons
+            .draw(gl, self.viewport, &self.last_view, &self.last_proj);
+        self.b_ribbons
+            .draw(gl, self.viewport, &self.last_view, &self.last_proj);
+        self.spheres
+            .draw(gl, self.point_size_px, &self.last_view, &self.last_proj);
+
+        match gl.get_error() {
+            Gl::NO_ERROR => Ok(()),
+            code => anyhow::bail!("WebGL error 0x{code:04x}"),
+        }
+    }
+
+    pub fn resize(&mut self, w: u32, h: u32) {
+        if w == 0 || h == 0 || (w, h) == self.size {
+            return;
+        }
+        self.size = (w, h);
+        self.viewport = [w as f32, h as f32];
+        self.canvas.set_width(w);
+        self.canvas.set_height(h);
+    }
+
+    pub fn clear_ribbons(&mut self) {
+        self.ribbons.strips.clear();
+        self.traced = None;
+    }
+
+    pub fn set_ribbons_b(&mut self, ribbons: &Ribbons) {
+        self.b_ribbons.upload(&self.gl, ribbons);
+    }
+
+    pub fn set_colormaps(&mut self, colormaps: Colormaps) {
+        self.ribbons.palette = colormaps.e;
+        self.b_ribbons.palette = colormaps.b;
+    }
+}
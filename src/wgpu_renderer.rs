@@ -1,21 +1,90 @@
-use crate::em3d::Charge3D;
-use crate::perf_gpu::GpuTimerRing;
-use leptos::logging::log;
+use crate::capture::{CaptureOptions, PendingCapture};
+use crate::field_grid::{FieldGrid, FieldSource};
+use crate::gpu_errors::{ErrorScope, ErrorSink};
+use crate::perf::Scope;
+use crate::perf_gpu::{GpuTimerRing, TimingSource};
+use em_viz_core::colormap::{Colormap, Colormaps};
+use em_viz_core::em3d::octree::{Octree, OctreeNode};
+use em_viz_core::em3d::{Charge3D, FieldEval};
+use em_viz_core::scene::Integrator;
+use em_viz_core::trace::Ribbons;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use web_sys::HtmlCanvasElement;
 use wgpu::{self, util::DeviceExt};
 
-// ------- Fixed pool sizes (tweak if needed) -------
-const MAX_STREAMS: u32 = 1024; // ribbons per dispatch
-const MAX_PTS: u32 = 1600; // RK steps per ribbon
-const MAX_CHARGES: u32 = 64; // max charges
-
-// Derived sizes
-const COUNT_BYTES: u64 = (MAX_STREAMS as u64) * 16; // draw indirect args per stream
-const SEEDS_BYTES: u64 = (MAX_STREAMS as u64) * 16; // vec4 per seed
-const CHARGES_BYTES: u64 = (MAX_CHARGES as u64) * 16; // vec4 per charge
+// ------- Pool sizing -------
+// The compute pools start small and grow on demand (see `PoolCaps::grow_to`).
+const INITIAL_STREAMS: u32 = 1024; // ribbons per dispatch
+const INITIAL_PTS: u32 = 400; // RK steps per ribbon
+const INITIAL_CHARGES: u32 = 64; // charges
+const INITIAL_NODES: u32 = 16; // octree nodes (Barnes–Hut only)
+
+// Per-element sizes
+const COUNT_STRIDE: u64 = 16; // draw indirect args per stream
+const SEED_STRIDE: u64 = 16; // vec4 per seed
+const CHARGE_STRIDE: u64 = 16; // vec4 per charge
+const NODE_STRIDE: u64 = 48; // 3 * vec4 per Barnes–Hut node
 // Each RK step emits two vertices; each vertex is 2 * vec4<f32> (packed like in WGSL)
 // => 2 verts * 2 vec4 * 16B = 64B per step
-const OUT_BYTES: u64 = (MAX_STREAMS as u64) * (MAX_PTS as u64) * 64;
+const STEP_STRIDE: u64 = 64;
+// Output vertex + count buffers are double-buffered: render draws the last
+// completed slot while compute fills the other one.
+const OUT_SLOTS: usize = 2;
+
+pub const DEFAULT_POOL_BUDGET_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Capacities of the ribbon compute pools, in elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PoolCaps {
+    streams: u32,
+    pts: u32,
+    charges: u32,
+    nodes: u32,
+}
+
+impl PoolCaps {
+    fn out_bytes(&self) -> u64 {
+        (self.streams as u64) * (self.pts as u64) * STEP_STRIDE
+    }
+    fn total_bytes(&self) -> u64 {
+        OUT_SLOTS as u64 * (self.out_bytes() + (self.streams as u64) * COUNT_STRIDE)
+            + (self.streams as u64) * SEED_STRIDE
+            + (self.charges as u64) * CHARGE_STRIDE
+            + (self.nodes as u64) * NODE_STRIDE
+    }
+    fn covers(&self, need: &PoolCaps) -> bool {
+        self.streams >= need.streams
+            && self.pts >= need.pts
+            && self.charges >= need.charges
+            && self.nodes >= need.nodes
+    }
+
+    /// Smallest caps covering both `self` and `need`, rounded up to powers of two
+    /// so repeated small increases don't reallocate every frame. Falls back to the
+    /// exact request when the rounded size would not fit in `max_bytes`.
+    fn grow_to(&self, need: &PoolCaps, max_bytes: u64) -> PoolCaps {
+        let rounded = PoolCaps {
+            streams: self.streams.max(need.streams.next_power_of_two()),
+            pts: self.pts.max(need.pts.next_power_of_two()),
+            charges: self.charges.max(need.charges.next_power_of_two()),
+            nodes: self.nodes.max(need.nodes.next_power_of_two()),
+        };
+        if rounded.total_bytes() <= max_bytes {
+            rounded
+        } else {
+            *need
+        }
+    }
+}
+
+fn pack_charge(c: &Charge3D) -> [f32; 4] {
+    [c.pos.x, c.pos.y, c.pos.z, c.q]
+}
+
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
@@ -60,7 +129,7 @@ impl RibbonV {
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 struct Instance {
     center: [f32; 3],
-    _pad: f32, // 16B alignment
+    selected: f32, // 0 or 1; also pads to 16B
 }
 impl Instance {
     fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
@@ -68,11 +137,18 @@ impl Instance {
         wgpu::VertexBufferLayout {
             array_stride: mem::size_of::<Instance>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &[wgpu::VertexAttribute {
-                shader_location: 2,
-                offset: 0,
-                format: wgpu::VertexFormat::Float32x3,
-            }],
+            attributes: &[
+                wgpu::VertexAttribute {
+                    shader_location: 2,
+                    offset: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    shader_location: 3,
+                    offset: 12,
+                    format: wgpu::VertexFormat::Float32,
+                },
+            ],
         }
     }
 }
@@ -106,6 +182,19 @@ const QUAD: [QuadV; 6] = [
     QuadV { pos: [-0.5, 0.5] },
 ];
 
+pub const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.02,
+    g: 0.02,
+    b: 0.05,
+    a: 1.0,
+};
+
+/// Half the on-screen ribbon width at a pixel scale of 1.
+pub const RIBBON_HALF_WIDTH_PX: f32 = 2.0;
+
+// The only multisample count (besides 1) WebGPU guarantees for render targets.
+pub const CAPTURE_MSAA: u32 = 4;
+
 const SPHERE_SHADER: &str = include_str!("../static/shaders/sphere.wgsl");
 const RIBBON_SHADER: &str = include_str!("../static/shaders/ribbon.wgsl");
 const RIBBON_COMP: &str = include_str!("../static/shaders/ribbon_e_comp.wgsl");
@@ -113,7 +202,7 @@ const RIBBON_COMP: &str = include_str!("../static/shaders/ribbon_e_comp.wgsl");
 #[derive(Debug)]
 pub struct Charges {
     pipeline: wgpu::RenderPipeline,
-    bind_layout: wgpu::BindGroupLayout,
+    pipeline_msaa: wgpu::RenderPipeline, // offscreen captures with CAPTURE_MSAA
     bind_group: wgpu::BindGroup,
     ubo: wgpu::Buffer, // view, proj, viewport.x/y, point_size
     vbuf_quad: wgpu::Buffer,
@@ -168,36 +257,8 @@ impl Charges {
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("charges pipeline"),
-            layout: Some(&pl),
-            vertex: wgpu::VertexState {
-                module: &smod,
-                entry_point: Some("vs"),
-                buffers: &[QuadV::layout(), Instance::layout()],
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &smod,
-                entry_point: Some("fs"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            cache: None,
-        });
+        let pipeline = Self::pipeline(device, &smod, &pl, format, 1);
+        let pipeline_msaa = Self::pipeline(device, &smod, &pl, format, CAPTURE_MSAA);
 
         // geometry buffers
         let vbuf_quad = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -215,7 +276,7 @@ impl Charges {
 
         Self {
             pipeline,
-            bind_layout,
+            pipeline_msaa,
             bind_group,
             ubo,
             vbuf_quad,
@@ -225,6 +286,48 @@ impl Charges {
         }
     }
 
+    fn pipeline(
+        device: &wgpu::Device,
+        smod: &wgpu::ShaderModule,
+        layout: &wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        samples: u32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("charges pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: smod,
+                entry_point: Some("vs"),
+                buffers: &[QuadV::layout(), Instance::layout()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: smod,
+                entry_point: Some("fs"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: samples,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
     pub fn update_viewproj(
         &self,
         queue: &wgpu::Queue,
@@ -246,13 +349,19 @@ impl Charges {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         charges: &[Charge3D],
+        selected: &[usize],
     ) {
         // NOTE: this Instance is the vertex-instancing struct you already defined above.
         let instances: Vec<Instance> = charges
             .iter()
-            .map(|c| Instance {
+            .enumerate()
+            .map(|(i, c)| Instance {
                 center: [c.pos.x, c.pos.y, c.pos.z],
-                _pad: 0.0,
+                selected: if selected.binary_search(&i).is_ok() {
+                    1.0
+                } else {
+                    0.0
+                },
             })
             .collect();
 
@@ -273,11 +382,15 @@ impl Charges {
         self.instances_len = instances.len() as u32;
     }
 
-    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, samples: u32) {
         if self.instances_len == 0 {
             return;
         }
-        rpass.set_pipeline(&self.pipeline);
+        rpass.set_pipeline(if samples > 1 {
+            &self.pipeline_msaa
+        } else {
+            &self.pipeline
+        });
         rpass.set_bind_group(0, &self.bind_group, &[]);
         rpass.set_vertex_buffer(0, self.vbuf_quad.slice(..));
         rpass.set_vertex_buffer(1, self.ibuf_instances.slice(..));
@@ -285,135 +398,144 @@ impl Charges {
     }
 }
 
-pub struct ERibbonsCompute {
-    pipeline: wgpu::ComputePipeline,
-    bind_layout: wgpu::BindGroupLayout,
-    bind_group: wgpu::BindGroup,
-    ubo: wgpu::Buffer, // k, soft2, h, max_pts, far_cut
-    buf_charges: wgpu::Buffer,
-    buf_seeds: wgpu::Buffer,
-    pub buf_counts: wgpu::Buffer,
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompParams {
+    k_soft_h_max: [f32; 4], // k, soft2, h, max_pts
+    far_cut: [f32; 4],      // far_cut, theta, 0, 0
+    counts: [u32; 4],       // n_charges, n_seeds, n_nodes, mode (0 direct, 1 Barnes–Hut)
 }
 
-impl ERibbonsCompute {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, ribbon_vbuf_e: &wgpu::Buffer) -> Self {
-        let comp_mod = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("ribbon_e_comp"),
-            source: wgpu::ShaderSource::Wgsl(RIBBON_COMP.into()),
-        });
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuNode {
+    center_q: [f32; 4], // xyz=expansion centre, w=total charge
+    dipole_r: [f32; 4], // xyz=dipole moment, w=radius
+    links: [u32; 4],    // next, first charge, charge count, leaf
+}
 
-        // bind layout: ubo + charges + seeds + out verts + counts
-        let bind_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("comp E layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    // UBO
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    // charges (RO)
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    // seeds (RO)
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    // OUT_VERTS (RW)
-                    binding: 3,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    // COUNTS (RW) + INDIRECT
-                    binding: 4,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
+impl From<&OctreeNode> for GpuNode {
+    fn from(n: &OctreeNode) -> Self {
+        Self {
+            center_q: [n.center.x, n.center.y, n.center.z, n.q],
+            dipole_r: [n.dipole.x, n.dipole.y, n.dipole.z, n.radius],
+            links: [n.next, n.start, n.count, n.leaf as u32],
+        }
+    }
+}
 
-        // pipeline
-        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("comp E pipeline"),
-            layout: Some(
-                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("comp E pl"),
-                    bind_group_layouts: &[&bind_layout],
-                    push_constant_ranges: &[],
-                }),
-            ),
-            module: &comp_mod,
-            entry_point: Some("main"),
-            compilation_options: Default::default(),
-            cache: None,
+/// Barnes–Hut input in upload order: the charges sorted into tree order and the
+/// packed nodes. Kept between dispatches, since building the tree is the main
+/// CPU cost per frame for large scenes.
+#[derive(Default)]
+struct PackedTree {
+    charges: Vec<[f32; 4]>,
+    nodes: Vec<GpuNode>,
+    // charges and leaf size the tree was built from; None until the first build
+    built: Option<(Vec<Charge3D>, usize)>,
+}
+
+impl PackedTree {
+    /// Rebuild unless `charges` and `leaf_size` match the last build.
+    fn build_if_stale(&mut self, charges: &[Charge3D], leaf_size: usize) {
+        if let Some((c, l)) = &self.built
+            && c.as_slice() == charges
+            && *l == leaf_size
+        {
+            return;
+        }
+        let tree = {
+            let _t = Scope::new(format!("octree.build n={}", charges.len()));
+            Octree::build(charges, leaf_size)
+        };
+        self.charges = tree.charges.iter().map(pack_charge).collect();
+        self.nodes = tree.nodes.iter().map(GpuNode::from).collect();
+        self.built = Some((charges.to_vec(), leaf_size));
+    }
+}
+
+/// One compute output: ribbon vertices plus their draw-indirect counts.
+struct OutSlot {
+    vbuf: wgpu::Buffer,   // compute writes / draw reads
+    counts: wgpu::Buffer, // draw indirect args per stream
+}
+
+impl OutSlot {
+    fn new(device: &wgpu::Device, caps: &PoolCaps, i: usize) -> Self {
+        let counts = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("counts[{i}]")),
+            size: (caps.streams as u64) * COUNT_STRIDE,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
 
-        // buffers
-        let ubo = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("comp ubo"),
-            size: 48, // 3 * vec4
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        let vbuf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("ribbon_vbuf_e[{i}]")),
+            size: caps.out_bytes(),
+            usage: wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
+        Self { vbuf, counts }
+    }
+}
+
+/// GPU buffers sized by [`PoolCaps`]; recreated together when they must grow.
+struct Pools {
+    buf_charges: wgpu::Buffer,
+    buf_seeds: wgpu::Buffer,
+    buf_nodes: wgpu::Buffer,
+    outs: [OutSlot; OUT_SLOTS],
+}
+
+impl Pools {
+    fn new(device: &wgpu::Device, caps: &PoolCaps) -> Self {
         let buf_charges = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("charges"),
-            size: CHARGES_BYTES,
+            size: (caps.charges as u64) * CHARGE_STRIDE,
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         let buf_seeds = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("seeds"),
-            size: SEEDS_BYTES,
+            size: (caps.streams as u64) * SEED_STRIDE,
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        let buf_counts = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("counts"),
-            size: COUNT_BYTES,
-            usage: wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::INDIRECT
-                | wgpu::BufferUsages::COPY_DST,
+        let buf_nodes = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("octree nodes"),
+            size: (caps.nodes as u64) * NODE_STRIDE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("comp E bg"),
-            layout: &bind_layout,
+        Self {
+            buf_charges,
+            buf_seeds,
+            buf_nodes,
+            outs: std::array::from_fn(|i| OutSlot::new(device, caps, i)),
+        }
+    }
+
+    /// Bind group writing into output slot `slot`; inputs are shared.
+    fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        ubo: &wgpu::Buffer,
+        slot: usize,
+    ) -> wgpu::BindGroup {
+        let out = &self.outs[slot];
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("comp E bg[{slot}]")),
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -421,81 +543,316 @@ impl ERibbonsCompute {
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: buf_charges.as_entire_binding(),
+                    resource: self.buf_charges.as_entire_binding(),
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
-                    resource: buf_seeds.as_entire_binding(),
+                    resource: self.buf_seeds.as_entire_binding(),
                 },
                 wgpu::BindGroupEntry {
                     binding: 3,
-                    resource: ribbon_vbuf_e.as_entire_binding(),
+                    resource: out.vbuf.as_entire_binding(),
                 },
                 wgpu::BindGroupEntry {
                     binding: 4,
-                    resource: buf_counts.as_entire_binding(),
+                    resource: out.counts.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: self.buf_nodes.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
+
+fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// A dispatch whose results are not yet known to be complete.
+struct InFlight {
+    slot: usize,
+    streams: u32,
+    done: Arc<AtomicBool>, // set by `Queue::on_submitted_work_done`
+}
+
+pub struct ERibbonsCompute {
+    pipeline: wgpu::ComputePipeline, // direct sum / Barnes–Hut (`main`)
+    pipeline_tiled: wgpu::ComputePipeline, // shared-memory tiles (`main_tiled`)
+    bind_layout: wgpu::BindGroupLayout,
+    bind_groups: [wgpu::BindGroup; OUT_SLOTS], // one per output slot
+    front: usize,                              // slot holding the last completed result
+    front_streams: u32,                        // ribbons in the front slot
+    in_flight: Option<InFlight>,
+    ubo: wgpu::Buffer, // k, soft2, h, max_pts, far_cut, theta, counts, mode
+    pools: Pools,
+    caps: PoolCaps,
+    budget_bytes: u64,
+    device_max_binding: u64,
+}
+
+impl ERibbonsCompute {
+    pub fn new(
+        device: &wgpu::Device,
+        grid_layout: &wgpu::BindGroupLayout,
+        budget_bytes: u64,
+    ) -> Self {
+        let comp_mod = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ribbon_e_comp"),
+            source: wgpu::ShaderSource::Wgsl(RIBBON_COMP.into()),
+        });
+
+        // bind layout: ubo + charges + seeds + out verts + counts + octree nodes
+        let bind_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("comp E layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    // UBO
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
+                storage_entry(1, true),  // charges (RO)
+                storage_entry(2, true),  // seeds (RO)
+                storage_entry(3, false), // OUT_VERTS (RW)
+                storage_entry(4, false), // COUNTS (RW) + INDIRECT
+                storage_entry(5, true),  // octree nodes (RO)
             ],
         });
 
+        // pipelines
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("comp E pl"),
+            bind_group_layouts: &[&bind_layout, grid_layout],
+            push_constant_ranges: &[],
+        });
+        let make_pipeline = |label: &str, entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                module: &comp_mod,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let pipeline = make_pipeline("comp E pipeline", "main");
+        let pipeline_tiled = make_pipeline("comp E tiled pipeline", "main_tiled");
+
+        let ubo = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("comp ubo"),
+            size: std::mem::size_of::<CompParams>() as u64, // 3 * vec4
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let limits = device.limits();
+        let device_max_binding =
+            (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+
+        // Start with the historical fixed pool, shrunk if the budget can't hold it.
+        let mut caps = PoolCaps {
+            streams: INITIAL_STREAMS,
+            pts: INITIAL_PTS,
+            charges: INITIAL_CHARGES,
+            nodes: INITIAL_NODES,
+        };
+        while caps.streams > 1
+            && (caps.total_bytes() > budget_bytes || caps.out_bytes() > device_max_binding)
+        {
+            caps.streams /= 2;
+        }
+
+        let pools = Pools::new(device, &caps);
+        let bind_groups = std::array::from_fn(|i| pools.bind_group(device, &bind_layout, &ubo, i));
+
         Self {
             pipeline,
+            pipeline_tiled,
             bind_layout,
-            bind_group,
+            bind_groups,
+            front: 0,
+            front_streams: 0,
+            in_flight: None,
             ubo,
-            buf_charges,
-            buf_seeds,
-            buf_counts,
+            pools,
+            caps,
+            budget_bytes,
+            device_max_binding,
         }
     }
 
-    pub fn upload_inputs(&mut self, queue: &wgpu::Queue, charges: &[[f32; 4]], seeds: &[[f32; 4]]) {
-        queue.write_buffer(&self.buf_charges, 0, bytemuck::cast_slice(charges));
-        queue.write_buffer(&self.buf_seeds, 0, bytemuck::cast_slice(seeds));
+    pub fn set_budget(&mut self, budget_bytes: u64) {
+        self.budget_bytes = budget_bytes;
     }
 
-    pub fn write_params(
-        &self,
+    /// Vertex buffer of the last completed dispatch.
+    pub fn vertex_buffer(&self) -> &wgpu::Buffer {
+        &self.pools.outs[self.front].vbuf
+    }
+
+    /// Draw-indirect counts of the last completed dispatch.
+    pub fn counts_buffer(&self) -> &wgpu::Buffer {
+        &self.pools.outs[self.front].counts
+    }
+
+    /// Ribbons in the last completed dispatch.
+    pub fn front_streams(&self) -> u32 {
+        self.front_streams
+    }
+
+    /// Whether a dispatch is still running on the GPU.
+    pub fn busy(&self) -> bool {
+        self.in_flight.is_some()
+    }
+
+    /// Promote the in-flight slot to front once its work-done callback has fired.
+    /// Returns `true` when the front slot changed.
+    pub fn poll_swap(&mut self) -> bool {
+        match &self.in_flight {
+            Some(job) if job.done.load(Ordering::Acquire) => {
+                self.front = job.slot;
+                self.front_streams = job.streams;
+                self.in_flight = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Drop the current result; the next completed dispatch repopulates it.
+    pub fn clear(&mut self) {
+        self.front_streams = 0;
+    }
+
+    fn back(&self) -> usize {
+        (self.front + 1) % OUT_SLOTS
+    }
+
+    /// Record that a dispatch of `streams` ribbons into the back slot was just
+    /// submitted; it becomes the front once the queue reports it done.
+    fn mark_submitted(&mut self, queue: &wgpu::Queue, streams: u32) {
+        let done = Arc::new(AtomicBool::new(false));
+        let flag = done.clone();
+        queue.on_submitted_work_done(move || flag.store(true, Ordering::Release));
+        self.in_flight = Some(InFlight {
+            slot: self.back(),
+            streams,
+            done,
+        });
+    }
+
+    /// Make sure the pools can hold `streams` ribbons of `pts` steps, `charges`
+    /// charges and `nodes` octree nodes, recreating buffers and bind groups if
+    /// they must grow. Must not be called while a dispatch is in flight. Returns
+    /// `true` when the buffers were replaced; the front result is lost then.
+    pub fn ensure_capacity(
+        &mut self,
+        device: &wgpu::Device,
+        streams: u32,
+        pts: u32,
+        charges: u32,
+        nodes: u32,
+    ) -> anyhow::Result<bool> {
+        let need = PoolCaps {
+            streams: streams.max(1),
+            pts: pts.max(1),
+            charges: charges.max(1),
+            nodes: nodes.max(1),
+        };
+        let max_bytes = self.budget_bytes;
+        if self.caps.covers(&need) && self.caps.total_bytes() <= max_bytes {
+            return Ok(false);
+        }
+
+        if need.total_bytes() > max_bytes {
+            anyhow::bail!(
+                "{} ribbons × {} pts need {:.1} MiB of GPU pool, budget is {:.1} MiB",
+                streams,
+                pts,
+                mib(need.total_bytes()),
+                mib(max_bytes)
+            );
+        }
+        if need.out_bytes() > self.device_max_binding {
+            anyhow::bail!(
+                "{} ribbons × {} pts need a {:.1} MiB vertex buffer, device limit is {:.1} MiB",
+                streams,
+                pts,
+                mib(need.out_bytes()),
+                mib(self.device_max_binding)
+            );
+        }
+
+        // Only keep the old caps as a floor while they still fit in the budget.
+        let floor = if self.caps.total_bytes() <= max_bytes {
+            self.caps
+        } else {
+            need
+        };
+        let mut caps = floor.grow_to(&need, max_bytes);
+        if caps.out_bytes() > self.device_max_binding {
+            caps = need;
+        }
+
+        debug_assert!(self.in_flight.is_none());
+        self.pools = Pools::new(device, &caps);
+        self.bind_groups = std::array::from_fn(|i| {
+            self.pools
+                .bind_group(device, &self.bind_layout, &self.ubo, i)
+        });
+        self.caps = caps;
+        self.front_streams = 0;
+        Ok(true)
+    }
+
+    fn upload_inputs(
+        &mut self,
         queue: &wgpu::Queue,
-        k: f32,
-        soft2: f32,
-        h: f32,
-        max_pts: u32,
-        far_cut: f32,
+        charges: &[[f32; 4]],
+        seeds: &[[f32; 4]],
+        nodes: &[GpuNode],
     ) {
-        queue.write_buffer(
-            &self.ubo,
-            0,
-            bytemuck::cast_slice(&[
-                k,
-                soft2,
-                h,
-                max_pts as f32,
-                far_cut,
-                0.0,
-                0.0,
-                0.0,
-                0.0,
-                0.0,
-                0.0,
-                0.0,
-            ]),
-        );
+        debug_assert!(charges.len() <= self.caps.charges as usize);
+        debug_assert!(seeds.len() <= self.caps.streams as usize);
+        debug_assert!(nodes.len() <= self.caps.nodes as usize);
+        queue.write_buffer(&self.pools.buf_charges, 0, bytemuck::cast_slice(charges));
+        queue.write_buffer(&self.pools.buf_seeds, 0, bytemuck::cast_slice(seeds));
+        queue.write_buffer(&self.pools.buf_nodes, 0, bytemuck::cast_slice(nodes));
+    }
+
+    fn write_params(&self, queue: &wgpu::Queue, params: &CompParams) {
+        queue.write_buffer(&self.ubo, 0, bytemuck::bytes_of(params));
     }
 }
 
+/// The ribbon shader's pipelines and uniforms, shared by the E and B layers.
 #[derive(Debug)]
-pub struct ERibbonsDraw {
-    ribbon_pipeline: wgpu::RenderPipeline,
-    bind_layout: wgpu::BindGroupLayout,
+struct RibbonPass {
+    pipeline: wgpu::RenderPipeline,
+    pipeline_msaa: wgpu::RenderPipeline, // offscreen captures with CAPTURE_MSAA
     bind_group: wgpu::BindGroup,
-    ubo: wgpu::Buffer,  // view, proj, viewport.x/y, halfWidth, alpha
-    vbuf: wgpu::Buffer, // OUT vertices written by compute
-    streams_active: u32,
+    ubo: wgpu::Buffer, // view, proj, viewport.x/y, halfWidth, alpha, palette
+    palette: Colormap,
 }
 
-impl ERibbonsDraw {
-    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, vbuf: wgpu::Buffer) -> Self {
+impl RibbonPass {
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat, palette: Colormap) -> Self {
         let ribbon_mod = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("ribbon shader"),
             source: wgpu::ShaderSource::Wgsl(RIBBON_SHADER.into()),
@@ -504,7 +861,7 @@ impl ERibbonsDraw {
         // UBO
         let ubo = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("ribbon ubo"),
-            size: 144, // view, proj, vec4(viewport.x, viewport.y, halfWidth, alpha)
+            size: 160, // view, proj, vec4(viewport.x, viewport.y, halfWidth, alpha), palette
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -539,21 +896,45 @@ impl ERibbonsDraw {
             push_constant_ranges: &[],
         });
 
-        let ribbon_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let pipeline = Self::pipeline(device, &ribbon_mod, &layout, format, 1);
+        let pipeline_msaa = Self::pipeline(device, &ribbon_mod, &layout, format, CAPTURE_MSAA);
+
+        Self {
+            pipeline,
+            pipeline_msaa,
+            bind_group,
+            ubo,
+            palette,
+        }
+    }
+
+    fn pipeline(
+        device: &wgpu::Device,
+        module: &wgpu::ShaderModule,
+        layout: &wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        samples: u32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("ribbon pipeline"),
-            layout: Some(&layout),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &ribbon_mod,
+                module,
                 entry_point: Some("vs"),
                 buffers: &[RibbonV::layout()],
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
-                module: &ribbon_mod,
+                module,
                 entry_point: Some("fs"),
                 targets: &[Some(wgpu::ColorTargetState {
                     format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    // alpha composites "over" so transparent captures come out
+                    // premultiplied; on an opaque target it stays 1
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendState::ALPHA_BLENDING.color,
+                        alpha: wgpu::BlendComponent::OVER,
+                    }),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: Default::default(),
@@ -565,47 +946,90 @@ impl ERibbonsDraw {
                 ..Default::default()
             },
             depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
+            multisample: wgpu::MultisampleState {
+                count: samples,
+                ..Default::default()
+            },
             multiview: None,
             cache: None,
+        })
+    }
+
+    /// Write the uniforms and set the pipeline for `samples`; `px_scale`
+    /// multiplies the on-screen ribbon width (offscreen captures larger than
+    /// the canvas).
+    #[allow(clippy::too_many_arguments)]
+    fn bind<'a>(
+        &'a self,
+        queue: &wgpu::Queue,
+        rpass: &mut wgpu::RenderPass<'a>,
+        viewport: [f32; 2],
+        px_scale: f32,
+        samples: u32,
+        view: [f32; 16],
+        proj: [f32; 16],
+    ) {
+        // write UBO (view, proj, viewport.x/y, halfWidth, alpha, palette)
+        let mut bytes = [0u8; 160];
+        bytes[0..64].copy_from_slice(bytemuck::cast_slice(&view));
+        bytes[64..128].copy_from_slice(bytemuck::cast_slice(&proj));
+        // tweak thickness/alpha here:
+        let v = [
+            viewport[0],
+            viewport[1],
+            RIBBON_HALF_WIDTH_PX * px_scale,
+            0.85,
+        ];
+        bytes[128..144].copy_from_slice(bytemuck::cast_slice(&v));
+        bytes[144..148].copy_from_slice(&self.palette.index().to_le_bytes());
+        queue.write_buffer(&self.ubo, 0, &bytes);
+
+        rpass.set_pipeline(if samples > 1 {
+            &self.pipeline_msaa
+        } else {
+            &self.pipeline
         });
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+    }
+}
+
+#[derive(Debug)]
+pub struct ERibbonsDraw {
+    pass: RibbonPass,
+    vbuf: wgpu::Buffer, // OUT vertices written by compute
+    streams_active: u32,
+}
 
+impl ERibbonsDraw {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, vbuf: wgpu::Buffer) -> Self {
         Self {
-            ribbon_pipeline,
-            bind_layout,
-            bind_group,
-            ubo,
+            pass: RibbonPass::new(device, format, Colormaps::default().e),
             vbuf,
             streams_active: 0,
         }
     }
 
+    /// `px_scale` multiplies the on-screen ribbon width (offscreen captures
+    /// larger than the canvas).
+    #[allow(clippy::too_many_arguments)]
     pub fn draw<'a>(
         &'a self,
         queue: &wgpu::Queue,
         rpass: &mut wgpu::RenderPass<'a>,
         counts: &'a wgpu::Buffer, // from compute
         viewport: [f32; 2],       // from renderer
-        view: [f32; 16],          // from renderer
-        proj: [f32; 16],          // from renderer
+        px_scale: f32,
+        samples: u32,
+        view: [f32; 16], // from renderer
+        proj: [f32; 16], // from renderer
     ) {
         if self.streams_active == 0 {
             return;
         }
 
-        // write UBO (view, proj, viewport.x/y, halfWidth, alpha)
-        let mut bytes = [0u8; 144];
-        bytes[0..64].copy_from_slice(bytemuck::cast_slice(&view));
-        bytes[64..128].copy_from_slice(bytemuck::cast_slice(&proj));
-        // tweak thickness/alpha here:
-        let v = [viewport[0], viewport[1], 2.0, 0.85];
-        bytes[128..144].copy_from_slice(bytemuck::cast_slice(&v));
-        queue.write_buffer(&self.ubo, 0, &bytes);
-
-        // draw
+        self.pass
+            .bind(queue, rpass, viewport, px_scale, samples, view, proj);
         rpass.set_vertex_buffer(0, self.vbuf.slice(..));
-        rpass.set_pipeline(&self.ribbon_pipeline);
-        rpass.set_bind_group(0, &self.bind_group, &[]);
         for i in 0..self.streams_active {
             let offset = (i as u64) * 16;
             rpass.draw_indirect(counts, offset);
@@ -615,6 +1039,77 @@ impl ERibbonsDraw {
     pub fn set_streams(&mut self, n: u32) {
         self.streams_active = n;
     }
+
+    pub fn set_vbuf(&mut self, vbuf: wgpu::Buffer) {
+        self.vbuf = vbuf;
+    }
+}
+
+/// B lines, traced on the CPU (only moving charges have any, so there are
+/// few) and drawn like the E ribbons in a palette of their own.
+#[derive(Debug)]
+pub struct BRibbonsDraw {
+    pass: RibbonPass,
+    vbuf: Option<wgpu::Buffer>, // grown to fit, never shrunk
+    strips: Vec<(u32, u32)>,
+}
+
+impl BRibbonsDraw {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        Self {
+            pass: RibbonPass::new(device, format, Colormaps::default().b),
+            vbuf: None,
+            strips: vec![],
+        }
+    }
+
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, ribbons: &Ribbons) {
+        let bytes: &[u8] = bytemuck::cast_slice(&ribbons.verts);
+        if self
+            .vbuf
+            .as_ref()
+            .is_none_or(|b| b.size() < bytes.len() as u64)
+        {
+            self.vbuf = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("ribbon_vbuf_b"),
+                size: (bytes.len() as u64).next_power_of_two().max(4096),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        if let Some(vbuf) = &self.vbuf {
+            queue.write_buffer(vbuf, 0, bytes);
+        }
+        self.strips = ribbons.strips.clone();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw<'a>(
+        &'a self,
+        queue: &wgpu::Queue,
+        rpass: &mut wgpu::RenderPass<'a>,
+        viewport: [f32; 2],
+        px_scale: f32,
+        samples: u32,
+        view: [f32; 16],
+        proj: [f32; 16],
+    ) {
+        let Some(vbuf) = &self.vbuf else {
+            return;
+        };
+        if self.strips.is_empty() {
+            return;
+        }
+
+        self.pass
+            .bind(queue, rpass, viewport, px_scale, samples, view, proj);
+        rpass.set_vertex_buffer(0, vbuf.slice(..));
+        for &(first, count) in &self.strips {
+            if count > 0 {
+                rpass.draw(first..first + count, 0..1);
+            }
+        }
+    }
 }
 
 pub struct WgpuRenderer {
@@ -631,8 +1126,12 @@ pub struct WgpuRenderer {
     charges: Charges,
     ecomp: ERibbonsCompute,
     edraw: ERibbonsDraw,
+    bdraw: BRibbonsDraw,
+    grid: FieldGrid,
+    tree: PackedTree,
 
     timer: GpuTimerRing,
+    errors: ErrorSink,
 }
 
 impl WgpuRenderer {
@@ -640,6 +1139,7 @@ impl WgpuRenderer {
         canvas: HtmlCanvasElement,
         initial_charges: &[Charge3D],
         point_px: f32,
+        errors: ErrorSink,
     ) -> anyhow::Result<Self> {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::BROWSER_WEBGPU,
@@ -655,12 +1155,23 @@ impl WgpuRenderer {
             })
             .await?;
 
+        let adapter_limits = adapter.limits();
+        // timestamp queries are optional; GpuTimerRing falls back without them
+        let features = adapter.features() & wgpu::Features::TIMESTAMP_QUERY;
         let (device, queue) = adapter
             .request_device(&wgpu::DeviceDescriptor {
-                required_features: wgpu::Features::TIMESTAMP_QUERY,
+                required_features: features,
+                // ask for the adapter's buffer limits so the pool budget can exceed
+                // the conservative WebGPU defaults where the hardware allows it
+                required_limits: wgpu::Limits {
+                    max_storage_buffer_binding_size: adapter_limits.max_storage_buffer_binding_size,
+                    max_buffer_size: adapter_limits.max_buffer_size,
+                    ..Default::default()
+                },
                 ..Default::default()
             })
             .await?;
+        errors.install(&device);
 
         let caps = surface.get_capabilities(&adapter);
         let format = caps
@@ -682,21 +1193,13 @@ impl WgpuRenderer {
         };
         surface.configure(&device, &config);
 
-        // shared ribbon vertex buffer (compute writes / draw reads)
-        let ribbon_vbuf_e = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("ribbon_vbuf_e"),
-            size: OUT_BYTES,
-            usage: wgpu::BufferUsages::VERTEX
-                | wgpu::BufferUsages::STORAGE
-                | wgpu::BufferUsages::COPY_SRC
-                | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        // sub-systems
+        // sub-systems; the ribbon vertex buffers are owned by the compute pools and
+        // the draw reads whichever one holds the last completed result
         let charges = Charges::new(&device, format);
-        let ecomp = ERibbonsCompute::new(&device, &queue, &ribbon_vbuf_e);
-        let edraw = ERibbonsDraw::new(&device, format, ribbon_vbuf_e.clone());
+        let grid = FieldGrid::new(&device);
+        let ecomp = ERibbonsCompute::new(&device, &grid.sample_layout, DEFAULT_POOL_BUDGET_BYTES);
+        let edraw = ERibbonsDraw::new(&device, format, ecomp.vertex_buffer().clone());
+        let bdraw = BRibbonsDraw::new(&device, format);
 
         let timer = GpuTimerRing::new(&device, &queue, "Ecomp");
 
@@ -713,12 +1216,16 @@ impl WgpuRenderer {
             charges,
             ecomp,
             edraw,
+            bdraw,
+            grid,
+            tree: PackedTree::default(),
             timer,
+            errors,
         };
 
         // initial charges upload (once)
         this.charges
-            .update_charges(&this.device, &this.queue, initial_charges);
+            .update_charges(&this.device, &this.queue, initial_charges, &[]);
 
         Ok(this)
     }
@@ -734,24 +1241,92 @@ impl WgpuRenderer {
         self.point_size_px = px.max(1.0);
     }
 
-    pub fn update_charges(&mut self, charges: &[Charge3D]) {
+    /// Upload charge positions; `selected` (sorted indices) are drawn
+    /// highlighted.
+    pub fn update_charges(&mut self, charges: &[Charge3D], selected: &[usize]) {
         self.charges
-            .update_charges(&self.device, &self.queue, charges);
+            .update_charges(&self.device, &self.queue, charges, selected);
+    }
+    /// Upper bound on GPU memory for the ribbon compute pools. Takes effect on the
+    /// next dispatch; pools larger than the new budget are shrunk then.
+    pub fn set_pool_budget(&mut self, bytes: u64) {
+        self.ecomp.set_budget(bytes);
     }
+
     pub fn start_compute_ribbons_e(
         &mut self,
-        charges: &[[f32; 4]],
+        charges: &[Charge3D],
         seeds: &[[f32; 4]],
-        h: f32,
-        max_pts: u32,
-    ) {
-        let k = 1.0f32;
-        let soft2 = 0.0025f32;
+        integrator: &Integrator,
+        source: FieldSource,
+    ) -> anyhow::Result<()> {
+        let &Integrator {
+            h,
+            max_pts,
+            k,
+            soft2,
+            eval,
+            ..
+        } = integrator;
         let far_cut = 250.0f32;
+
+        // Render keeps drawing the previous result until the running dispatch
+        // lands; the next request is simply taken up on a later frame.
+        self.sync_front();
+        if self.ecomp.busy() {
+            return Ok(());
+        }
+
+        if let FieldSource::Baked(settings) = source {
+            let scope = ErrorScope::push(&self.device, "bake");
+            self.grid.bake_if_stale(
+                &self.device,
+                &self.queue,
+                &mut self.timer,
+                charges,
+                settings,
+                k,
+                soft2,
+            );
+            scope.pop(&self.device, &self.errors);
+        }
+        let baked = matches!(source, FieldSource::Baked(_));
+        self.grid.set_enabled(&self.queue, baked);
+
+        // Barnes–Hut walks charges in tree order, the other paths take them as-is
+        let direct: Vec<[f32; 4]>;
+        let (charges4, nodes, theta, mode): (&[[f32; 4]], &[GpuNode], f32, u32) = match eval {
+            FieldEval::BarnesHut { theta } => {
+                self.tree.build_if_stale(charges, Octree::DEFAULT_LEAF_SIZE);
+                (&self.tree.charges, &self.tree.nodes, theta, 1)
+            }
+            FieldEval::Direct | FieldEval::Tiled => {
+                direct = charges.iter().map(pack_charge).collect();
+                (&direct, &[], 0.0, 0)
+            }
+        };
+
+        let n_charges = charges4.len() as u32;
+        let n_seeds = seeds.len() as u32;
+        let n_nodes = nodes.len() as u32;
+        if self
+            .ecomp
+            .ensure_capacity(&self.device, n_seeds, max_pts, n_charges, n_nodes)?
+        {
+            self.edraw.set_vbuf(self.ecomp.vertex_buffer().clone());
+            self.edraw.set_streams(0);
+        }
+        self.ecomp.write_params(
+            &self.queue,
+            &CompParams {
+                k_soft_h_max: [k, soft2, h, max_pts as f32],
+                far_cut: [far_cut, theta, 0.0, 0.0],
+                counts: [n_charges, n_seeds, n_nodes, mode],
+            },
+        );
         self.ecomp
-            .write_params(&self.queue, k, soft2, h, max_pts, far_cut);
-        self.ecomp.upload_inputs(&self.queue, charges, seeds);
-        // self.queue.submit([]);
+            .upload_inputs(&self.queue, charges4, seeds, nodes);
+        let scope = ErrorScope::push(&self.device, "compute");
         let mut enc = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -761,18 +1336,34 @@ impl WgpuRenderer {
         {
             let mut c = enc.begin_compute_pass(&wgpu::ComputePassDescriptor {
                 label: Some("Ribbon compute pass descriptor"),
-                timestamp_writes: Some(ts_writes),
+                timestamp_writes: ts_writes,
             });
-            c.set_pipeline(&self.ecomp.pipeline);
-            c.set_bind_group(0, &self.ecomp.bind_group, &[]);
-            let groups = (seeds.len() as u32).div_ceil(64);
+            // grid lookups are per-invocation, so a baked source always uses `main`
+            c.set_pipeline(match eval {
+                FieldEval::Tiled if !baked => &self.ecomp.pipeline_tiled,
+                _ => &self.ecomp.pipeline,
+            });
+            c.set_bind_group(0, &self.ecomp.bind_groups[self.ecomp.back()], &[]);
+            c.set_bind_group(1, &self.grid.sample_bind_group, &[]);
+            let groups = n_seeds.div_ceil(64);
             c.dispatch_workgroups(groups, 1, 1);
         } // compute pass dropped to drop the encoders borrow!
         finalize(&self.queue, enc);
-        self.edraw.set_streams(seeds.len() as u32);
+        scope.pop(&self.device, &self.errors);
+        self.ecomp.mark_submitted(&self.queue, n_seeds);
+        Ok(())
+    }
+
+    /// Point the ribbon draw at the newest completed compute output.
+    fn sync_front(&mut self) {
+        if self.ecomp.poll_swap() {
+            self.edraw.set_vbuf(self.ecomp.vertex_buffer().clone());
+            self.edraw.set_streams(self.ecomp.front_streams());
+        }
     }
 
     pub fn render(&mut self) -> anyhow::Result<()> {
+        self.sync_front();
         let frame = match self.surface.get_current_texture() {
             Ok(f) => f,
             Err(_) => {
@@ -783,6 +1374,7 @@ impl WgpuRenderer {
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        let scope = ErrorScope::push(&self.device, "render");
         let mut enc = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("enc") });
@@ -794,19 +1386,13 @@ impl WgpuRenderer {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.02,
-                            g: 0.02,
-                            b: 0.05,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(CLEAR_COLOR),
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: Some(ts_writes),
-                // timestamp_writes: None,
+                timestamp_writes: ts_writes,
                 occlusion_query_set: None,
             });
 
@@ -814,21 +1400,213 @@ impl WgpuRenderer {
             self.edraw.draw(
                 &self.queue,
                 &mut rpass,
-                &self.ecomp.buf_counts,
+                self.ecomp.counts_buffer(),
                 self.viewport,
+                1.0,
+                1,
+                self.last_view,
+                self.last_proj,
+            );
+            self.bdraw.draw(
+                &self.queue,
+                &mut rpass,
+                self.viewport,
+                1.0,
+                1,
                 self.last_view,
                 self.last_proj,
             );
 
             // spheres
-            self.charges.draw(&mut rpass);
+            self.charges.draw(&mut rpass, 1);
         }
-        // self.queue.submit(Some(enc.finish()));
         finalize(&self.queue, enc);
+        scope.pop(&self.device, &self.errors);
         frame.present();
         Ok(())
     }
 
+    /// Draw the current ribbons and charges into an offscreen target of
+    /// `opts.render_size()` and queue a copy into a readback buffer. Pixel sizes
+    /// (ribbon width, impostor size) scale with the target height relative to the
+    /// canvas, so the still looks like the canvas at a higher resolution.
+    pub fn capture(
+        &mut self,
+        opts: &CaptureOptions,
+        view: [f32; 16],
+        proj: [f32; 16],
+    ) -> anyhow::Result<PendingCapture> {
+        let (w, h) = opts.render_size();
+        let limits = self.device.limits();
+        if w == 0 || h == 0 {
+            anyhow::bail!("capture size must be non-zero");
+        }
+        if w.max(h) > limits.max_texture_dimension_2d {
+            anyhow::bail!(
+                "{w}×{h} exceeds the GPU's {} px texture limit; lower the size or supersampling",
+                limits.max_texture_dimension_2d
+            );
+        }
+        let row_bytes = (w * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer_bytes = row_bytes as u64 * h as u64;
+        if buffer_bytes > limits.max_buffer_size {
+            anyhow::bail!(
+                "readback of {:.0} MiB exceeds the GPU's buffer limit ({:.0} MiB)",
+                mib(buffer_bytes),
+                mib(limits.max_buffer_size)
+            );
+        }
+
+        self.sync_front();
+        let format = self.config.format;
+        let samples = if opts.msaa > 1 { CAPTURE_MSAA } else { 1 };
+        let target = |label, samples, usage| {
+            self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: samples,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let resolved = target(
+            "capture color",
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+        let msaa = (samples > 1).then(|| {
+            target(
+                "capture msaa",
+                samples,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        });
+        let resolved_view = resolved.create_view(&Default::default());
+        let msaa_view = msaa.as_ref().map(|t| t.create_view(&Default::default()));
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("capture readback"),
+            size: buffer_bytes,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let viewport = [w as f32, h as f32];
+        let px_scale = h as f32 / self.size.1.max(1) as f32;
+        self.charges.update_viewproj(
+            &self.queue,
+            viewport,
+            self.point_size_px * px_scale,
+            view,
+            proj,
+        );
+
+        let scope = ErrorScope::push(&self.device, "capture");
+        let mut enc = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("capture enc"),
+            });
+        {
+            let (color_view, resolve_target) = match &msaa_view {
+                Some(m) => (m, Some(&resolved_view)),
+                None => (&resolved_view, None),
+            };
+            let mut rpass = enc.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("capture rpass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: color_view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(if opts.transparent {
+                            wgpu::Color::TRANSPARENT
+                        } else {
+                            CLEAR_COLOR
+                        }),
+                        store: if resolve_target.is_some() {
+                            wgpu::StoreOp::Discard
+                        } else {
+                            wgpu::StoreOp::Store
+                        },
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.edraw.draw(
+                &self.queue,
+                &mut rpass,
+                self.ecomp.counts_buffer(),
+                viewport,
+                px_scale,
+                samples,
+                view,
+                proj,
+            );
+            self.bdraw.draw(
+                &self.queue,
+                &mut rpass,
+                viewport,
+                px_scale,
+                samples,
+                view,
+                proj,
+            );
+            self.charges.draw(&mut rpass, samples);
+        }
+        enc.copy_texture_to_buffer(
+            resolved.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row_bytes),
+                    rows_per_image: Some(h),
+                },
+            },
+            resolved.size(),
+        );
+        self.queue.submit([enc.finish()]);
+        scope.pop(&self.device, &self.errors);
+
+        // back to the canvas settings for the next frame
+        self.charges.update_viewproj(
+            &self.queue,
+            self.viewport,
+            self.point_size_px,
+            self.last_view,
+            self.last_proj,
+        );
+
+        Ok(PendingCapture {
+            buffer,
+            row_bytes,
+            bgra: matches!(
+                format,
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            ),
+            opts: *opts,
+        })
+    }
+
+    /// Whether a ribbon dispatch is still running; promotes it when done.
+    pub fn ribbons_pending(&mut self) -> bool {
+        self.sync_front();
+        self.ecomp.busy()
+    }
+
+    pub fn timing_source(&self) -> TimingSource {
+        self.timer.source()
+    }
+
     pub fn resize(&mut self, w: u32, h: u32) {
         if w == 0 || h == 0 || (w, h) == self.size {
             return;
@@ -840,6 +1618,17 @@ impl WgpuRenderer {
         self.surface.configure(&self.device, &self.config);
     }
     pub fn clear_ribbons(&mut self) {
+        self.ecomp.clear();
         self.edraw.set_streams(0);
     }
+
+    /// Show CPU-traced B ribbons; empty ones hide the layer.
+    pub fn set_ribbons_b(&mut self, ribbons: &Ribbons) {
+        self.bdraw.upload(&self.device, &self.queue, ribbons);
+    }
+
+    pub fn set_colormaps(&mut self, colormaps: Colormaps) {
+        self.edraw.pass.palette = colormaps.e;
+        self.bdraw.pass.palette = colormaps.b;
+    }
 }
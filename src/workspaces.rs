@@ -0,0 +1,95 @@
+//! Scene persistence in `localStorage`: a rolling autosave of the current
+//! session plus named workspaces, all stored as scene-file JSON so they are read
+//! like saved files.
+
+use crate::history;
+use crate::state::AppState;
+use anyhow::{Context, Result, anyhow};
+use em_viz_core::scene_file::{SceneFile, Syntax};
+use leptos::prelude::*;
+use leptos_use::{DebounceOptions, use_debounce_fn_with_options};
+use web_sys::Storage;
+
+const AUTOSAVE_KEY: &str = "em_viz.autosave";
+const WORKSPACE_PREFIX: &str = "em_viz.workspace:";
+
+fn storage() -> Result<Storage> {
+    window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .context("localStorage is unavailable")
+}
+
+fn read(key: &str) -> Result<Option<SceneFile>> {
+    let Some(text) = storage()?.get_item(key).ok().flatten() else {
+        return Ok(None);
+    };
+    SceneFile::parse(&text, Syntax::Json)
+        .map(Some)
+        .map_err(anyhow::Error::msg)
+}
+
+fn write(key: &str, file: &SceneFile) -> Result<()> {
+    storage()?
+        .set_item(key, &file.to_text(Syntax::Json))
+        .map_err(|e| anyhow!("storage full or blocked: {e:?}"))
+}
+
+/// The last autosaved session, if there is a readable one.
+pub fn autosaved() -> Option<SceneFile> {
+    read(AUTOSAVE_KEY).ok().flatten()
+}
+
+/// Save the session shortly after every change while `enabled` is set.
+pub fn attach_autosave(app: AppState, enabled: Signal<bool>) {
+    let save = use_debounce_fn_with_options(
+        move || {
+            if let Err(e) = write(AUTOSAVE_KEY, &app.scene_file()) {
+                leptos::logging::warn!("autosave failed: {e}");
+            }
+        },
+        500.0,
+        DebounceOptions::default().max_wait(Some(3000.0)),
+    );
+    Effect::new(move |_| {
+        app.track_scene();
+        if enabled.get() {
+            save();
+        }
+    });
+}
+
+/// Names of the saved workspaces, sorted.
+pub fn list() -> Vec<String> {
+    let Ok(storage) = storage() else {
+        return vec![];
+    };
+    let len = storage.length().unwrap_or(0);
+    let mut names: Vec<String> = (0..len)
+        .filter_map(|i| storage.key(i).ok().flatten())
+        .filter_map(|key| key.strip_prefix(WORKSPACE_PREFIX).map(str::to_owned))
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn save(app: &AppState, name: &str) -> Result<()> {
+    let name = name.trim();
+    anyhow::ensure!(!name.is_empty(), "give the workspace a name");
+    write(&format!("{WORKSPACE_PREFIX}{name}"), &app.scene_file())
+}
+
+pub fn open(app: &AppState, name: &str) -> Result<()> {
+    let file = read(&format!("{WORKSPACE_PREFIX}{name}"))
+        .with_context(|| format!("workspace `{name}`"))?
+        .with_context(|| format!("workspace `{name}` is gone"))?;
+    history::open(app, file);
+    Ok(())
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    storage()?
+        .remove_item(&format!("{WORKSPACE_PREFIX}{name}"))
+        .map_err(|e| anyhow!("{e:?}"))
+}
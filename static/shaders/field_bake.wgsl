@@ -0,0 +1,38 @@
+struct Params {
+  lo_k: vec4<f32>,      // xyz = grid min corner, w = k
+  size_soft: vec4<f32>, // xyz = grid extent, w = soft2
+  dims: vec4<u32>,      // res, n_charges, 0, 0
+};
+@group(0) @binding(0) var<uniform> P: Params;
+
+struct Charge {
+  posq: vec4<f32>, // xyz=pos, w=q
+};
+struct Charges { data: array<Charge> }
+@group(0) @binding(1) var<storage, read> CH: Charges;
+
+@group(0) @binding(2) var OUT_E: texture_storage_3d<rgba16float, write>; // xyz=E, w unused
+
+// One invocation per voxel; values are taken at voxel centres so trilinear
+// sampling at uvw = (p - lo) / size reproduces them exactly.
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let res = P.dims.x;
+  if (any(gid >= vec3<u32>(res))) { return; }
+
+  let k     = P.lo_k.w;
+  let soft2 = P.size_soft.w;
+  let p = P.lo_k.xyz + (vec3<f32>(gid) + 0.5) / f32(res) * P.size_soft.xyz;
+
+  var e = vec3<f32>(0.0);
+  let n = min(P.dims.y, arrayLength(&CH.data));
+  for (var i: u32 = 0u; i < n; i = i + 1u) {
+    let c  = CH.data[i];
+    let d  = p - c.posq.xyz;
+    let r2 = dot(d, d) + soft2;
+    let r  = sqrt(r2);
+    e = e + (k * c.posq.w / (r2 * r)) * d; // d / r^3
+  }
+
+  textureStore(OUT_E, gid, vec4<f32>(e, 0.0));
+}
@@ -2,6 +2,7 @@ struct U {
   view: mat4x4<f32>,
   proj: mat4x4<f32>,
   vp_hw_alpha: vec4<f32>,  // x = viewport.w, y = viewport.h, z = halfWidthPx, w = alpha
+  palette: vec4<u32>,      // x = 0 viridis, 1 plasma
 };
 @group(0) @binding(0) var<uniform> UBO: U;
 
@@ -78,17 +79,36 @@ fn viridis(t: f32) -> vec3<f32> {
   let x = clamp(t,0.0,1.0)*5.0;
   let i = floor(x);
   let f = fract(x);
-  return select(
-    select(select(select(mix(c0,c1,f), mix(c1,c2,f), i<2.0), mix(c2,c3,f), i<3.0), mix(c3,c4,f), i<4.0),
-    mix(c4,c5,f),
-    i>=4.0
-  );
+  if (i < 1.0) { return mix(c0,c1,f); }
+  else if (i < 2.0) { return mix(c1,c2,f); }
+  else if (i < 3.0) { return mix(c2,c3,f); }
+  else if (i < 4.0) { return mix(c3,c4,f); }
+  return mix(c4,c5,f);
+}
+
+fn plasma(t: f32) -> vec3<f32> {
+  // coarse 6-stop plasma
+  let c0=vec3<f32>(0.050,0.030,0.527);
+  let c1=vec3<f32>(0.302,0.005,0.705);
+  let c2=vec3<f32>(0.553,0.053,0.741);
+  let c3=vec3<f32>(0.792,0.227,0.643);
+  let c4=vec3<f32>(0.956,0.427,0.397);
+  let c5=vec3<f32>(0.987,0.772,0.258);
+  let x = clamp(t,0.0,1.0)*5.0;
+  let i = floor(x);
+  let f = fract(x);
+  if (i < 1.0) { return mix(c0,c1,f); }
+  else if (i < 2.0) { return mix(c1,c2,f); }
+  else if (i < 3.0) { return mix(c2,c3,f); }
+  else if (i < 4.0) { return mix(c3,c4,f); }
+  return mix(c4,c5,f);
 }
 
 @fragment
 fn fs(@location(0) side: f32, @location(1) tone: f32) -> @location(0) vec4<f32> {
   let w = fwidth(side);
   let edge = 1.0 - smoothstep(1.0 - w, 1.0, abs(side));
-  let col = viridis(tone);
+  var col: vec3<f32>;
+  if (UBO.palette.x == 0u) { col = viridis(tone); } else { col = plasma(tone); }
   return vec4(col, edge * UBO.vp_hw_alpha.w);
 }
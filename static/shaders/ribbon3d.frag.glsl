@@ -6,7 +6,7 @@ in float v_side;
 out vec4 outColor;
 
 uniform float u_alpha;
-uniform int u_palette; // 0 = Viridis (E), 1 = Plasma (B)
+uniform int u_palette; // 0 = Viridis, 1 = Plasma
 
 vec3 viridis(float t){
   const vec3 c0=vec3(0.267,0.005,0.329), c1=vec3(0.283,0.141,0.458),
@@ -1,9 +1,8 @@
 struct Uniforms {
   k_soft_h_max: vec4<f32>, // k, soft2, h, max_pts
-  far_cut: vec4<f32>, // far_cut, 0, 0, 0
-  _pad0: vec4<f32>, // padding
+  far_cut: vec4<f32>, // far_cut, theta (Barnes–Hut opening angle), 0, 0
+  counts: vec4<u32>, // n_charges, n_seeds, n_nodes (buffers may be larger), mode (0 direct, 1 Barnes–Hut)
 };
-@group(0) @binding(0) var<uniform> P: Uniforms;
 
 struct Charge { posq: vec4<f32> };    // xyz=pos, w=q
 struct Charges { data: array<Charge> }
@@ -18,18 +17,40 @@ struct DrawIndirect {
 }
 struct Counts { data: array<DrawIndirect> }   // indirect draw args per streamline
 
+// Barnes–Hut octree in depth-first preorder (see em3d::octree)
+struct Node {
+  center_q: vec4<f32>, // xyz=expansion centre, w=total charge
+  dipole_r: vec4<f32>, // xyz=dipole moment, w=radius
+  links: vec4<u32>,    // next (skip subtree), first charge, charge count, leaf
+};
+struct Nodes { data: array<Node> }
+
 @group(0) @binding(0) var<uniform> U  : Uniforms;
 @group(0) @binding(1) var<storage, read>  CH: Charges;
 @group(0) @binding(2) var<storage, read>  SD: Seeds;
 @group(0) @binding(3) var<storage, read_write> OUT: OutBuf;
 @group(0) @binding(4) var<storage, read_write> CNT: Counts;
+@group(0) @binding(5) var<storage, read>  TREE: Nodes;
+
+// Optional baked field (see field_grid.rs / field_bake.wgsl)
+struct GridParams {
+  lo_on: vec4<f32>,    // xyz = grid min corner, w = 1 when the grid is in use
+  inv_size: vec4<f32>, // xyz = 1 / grid extent
+};
+@group(1) @binding(0) var<uniform> G: GridParams;
+@group(1) @binding(1) var GRID_E: texture_3d<f32>; // xyz=E
+@group(1) @binding(2) var GRID_SAMP: sampler;
+
+const TILE_SIZE: u32 = 64u; // == workgroup size
+var<workgroup> TILE: array<vec4<f32>, TILE_SIZE>;
+var<workgroup> ANY_ALIVE: u32;
 
 fn charges_len() -> u32 {
-  return arrayLength(&CH.data);
+  return min(U.counts.x, arrayLength(&CH.data));
 }
 
 fn seeds_len() -> u32 {
-  return arrayLength(&SD.data);
+  return min(U.counts.y, arrayLength(&SD.data));
 }
 
 fn sample_e(p: vec3<f32>) -> vec3<f32> {
@@ -50,6 +71,92 @@ fn sample_e(p: vec3<f32>) -> vec3<f32> {
   return e;
 }
 
+// Same sum as sample_e, but the workgroup walks the charges in tiles staged in
+// shared memory. Must be called from uniform control flow (barriers).
+fn sample_e_tiled(p: vec3<f32>, lid: u32) -> vec3<f32> {
+  let k     = U.k_soft_h_max.x;
+  let soft2 = U.k_soft_h_max.y;
+  let n = charges_len();
+  var e = vec3<f32>(0.0);
+  var base: u32 = 0u;
+  loop {
+    if (base >= n) { break; }
+    let j = base + lid;
+    var c = vec4<f32>(0.0);
+    if (j < n) { c = CH.data[j].posq; }
+    TILE[lid] = c;
+    workgroupBarrier();
+
+    let m = min(TILE_SIZE, n - base);
+    for (var i: u32 = 0u; i < m; i = i + 1u) {
+      let t  = TILE[i];
+      let d  = p - t.xyz;
+      let r2 = dot(d, d) + soft2;
+      let r  = sqrt(r2);
+      e = e + (k * t.w / (r2 * r)) * d;
+    }
+    workgroupBarrier();
+    base = base + TILE_SIZE;
+  }
+  return e;
+}
+
+// Barnes–Hut: accept a node as monopole + dipole when radius < theta * dist,
+// otherwise open it (leaves are summed directly). Charges are in tree order.
+fn sample_e_bh(p: vec3<f32>) -> vec3<f32> {
+  let k      = U.k_soft_h_max.x;
+  let soft2  = U.k_soft_h_max.y;
+  let theta  = U.far_cut.y;
+  let n      = min(U.counts.z, arrayLength(&TREE.data));
+  var e = vec3<f32>(0.0);
+  var i: u32 = 0u;
+  loop {
+    if (i >= n) { break; }
+    let nd  = TREE.data[i];
+    let d   = p - nd.center_q.xyz;
+    let rad = nd.dipole_r.w;
+    if (rad * rad < theta * theta * dot(d, d)) {
+      let inv  = inverseSqrt(dot(d, d) + soft2);
+      let inv3 = inv * inv * inv;
+      let inv5 = inv3 * inv * inv;
+      let dip  = nd.dipole_r.xyz;
+      e = e + k * (nd.center_q.w * inv3 * d + 3.0 * dot(dip, d) * inv5 * d - inv3 * dip);
+      i = nd.links.x;
+    } else if (nd.links.w == 1u) {
+      let end = min(nd.links.y + nd.links.z, charges_len());
+      for (var j: u32 = nd.links.y; j < end; j = j + 1u) {
+        let c  = CH.data[j].posq;
+        let dc = p - c.xyz;
+        let r2 = dot(dc, dc) + soft2;
+        let r  = sqrt(r2);
+        e = e + (k * c.w / (r2 * r)) * dc;
+      }
+      i = nd.links.x;
+    } else {
+      i = i + 1u;
+    }
+  }
+  return e;
+}
+
+// field used by `main`: the baked grid where it covers p, otherwise
+// counts.w selects 0 = direct sum, 1 = Barnes–Hut
+fn sample_field(p: vec3<f32>) -> vec3<f32> {
+  if (G.lo_on.w > 0.5) {
+    let uvw = (p - G.lo_on.xyz) * G.inv_size.xyz;
+    if (all(uvw >= vec3<f32>(0.0)) && all(uvw <= vec3<f32>(1.0))) {
+      return textureSampleLevel(GRID_E, GRID_SAMP, uvw, 0.0).xyz;
+    }
+  }
+  if (U.counts.w == 1u) { return sample_e_bh(p); }
+  return sample_e(p);
+}
+
+fn dir_of(e: vec3<f32>, sign: f32) -> vec3<f32> {
+  if (length(e) > 1e-6) { return normalize(e) * sign; }
+  return vec3<f32>(0.0);
+}
+
 fn tone_from_mag(m: f32) -> f32 {
   return pow(m / (1.0 + m), 0.75);
 }
@@ -86,7 +193,7 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
     if (step >= max_pts) { break; }
 
     // f(p): k1
-    let e1 = sample_e(p);
+    let e1 = sample_field(p);
     let m1 = length(e1);
 
     var k1: vec3<f32>;
@@ -97,7 +204,7 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
     }
 
     // k2
-    let e2 = sample_e(p + 0.5 * h * k1);
+    let e2 = sample_field(p + 0.5 * h * k1);
     var k2: vec3<f32>;
     if (length(e2) > 1e-6) {
       k2 = normalize(e2) * sign;
@@ -106,7 +213,7 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
     }
 
     // k3
-    let e3 = sample_e(p + 0.5 * h * k2);
+    let e3 = sample_field(p + 0.5 * h * k2);
     var k3: vec3<f32>;
     if (length(e3) > 1e-6) {
       k3 = normalize(e3) * sign;
@@ -115,7 +222,7 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
     }
 
     // k4
-    let e4 = sample_e(p + h * k3);
+    let e4 = sample_field(p + h * k3);
     var k4: vec3<f32>;
     if (length(e4) > 1e-6) {
       k4 = normalize(e4) * sign;
@@ -166,3 +273,72 @@ fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
   CNT.data[idx].first_vertex = base_vertex;
   CNT.data[idx].first_instance = 0u;
 }
+
+// Tiled variant of `main` for many charges. Every invocation of the workgroup
+// must reach the barriers in `sample_e_tiled`, so instead of returning early
+// finished (or out-of-range) streams keep stepping with `alive = false`, and the
+// loop only exits once the whole workgroup is done.
+@compute @workgroup_size(64)
+fn main_tiled(
+  @builtin(global_invocation_id) gid: vec3<u32>,
+  @builtin(local_invocation_index) lid: u32,
+) {
+  let idx = gid.x;
+  let n_seeds = seeds_len();
+  let in_range = idx < n_seeds;
+
+  let h       = U.k_soft_h_max.z;
+  let max_pts = u32(U.k_soft_h_max.w + 0.5);
+
+  var seed = vec4<f32>(0.0);
+  if (in_range) { seed = SD.data[idx]; }
+  var p    = seed.xyz;
+  var prev = p + vec3<f32>(0.0, 0.0, 1.0);
+  let sign = seed.w;
+
+  let base_vertex = idx * max_pts * 2u;
+  var written: u32 = 0u;
+  var alive = in_range;
+
+  var step: u32 = 0u;
+  loop {
+    if (step >= max_pts) { break; }
+    if (lid == 0u) { ANY_ALIVE = 0u; }
+    workgroupBarrier();
+    if (alive) { ANY_ALIVE = 1u; }
+    if (workgroupUniformLoad(&ANY_ALIVE) == 0u) { break; }
+
+    let e1 = sample_e_tiled(p, lid);
+    let m1 = length(e1);
+    let k1 = dir_of(e1, sign);
+    let k2 = dir_of(sample_e_tiled(p + 0.5 * h * k1, lid), sign);
+    let k3 = dir_of(sample_e_tiled(p + 0.5 * h * k2, lid), sign);
+    let k4 = dir_of(sample_e_tiled(p + h * k3, lid), sign);
+
+    if (alive) {
+      let p2   = p + h * (k1 + 2.0 * k2 + 2.0 * k3 + k4) / 6.0;
+      let tone = tone_from_mag(m1);
+      let tan  = normalize(p2 - prev);
+      prev = p;
+      p    = p2;
+
+      let base_vec4 = (base_vertex + written) * 2u;
+      OUT.data[base_vec4 + 0u] = vec4<f32>(p,  -1.0);
+      OUT.data[base_vec4 + 1u] = vec4<f32>(tan, tone);
+      OUT.data[base_vec4 + 2u] = vec4<f32>(p,   1.0);
+      OUT.data[base_vec4 + 3u] = vec4<f32>(tan, tone);
+      written = written + 2u;
+
+      if (!(m1 >= 1e-6 && m1 <= 1e4)) { alive = false; }
+    }
+
+    step = step + 1u;
+  }
+
+  if (in_range) {
+    CNT.data[idx].vertex_count = written;
+    CNT.data[idx].instance_count = select(0u, 1u, written > 0u);
+    CNT.data[idx].first_vertex = base_vertex;
+    CNT.data[idx].first_instance = 0u;
+  }
+}
@@ -9,11 +9,13 @@ struct Uniforms {
 struct VsIn {
   @location(0) quad: vec2<f32>,   // -0.5..+0.5
   @location(2) center: vec3<f32>,
+  @location(3) selected: f32,     // 0 or 1
 };
 
 struct VsOut {
   @builtin(position) pos: vec4<f32>,
   @location(0) quad: vec2<f32>,
+  @location(1) selected: f32,
 };
 
 @vertex
@@ -36,11 +38,12 @@ fn vs(in_: VsIn) -> VsOut {
 
   o.pos = clip;
   o.quad = in_.quad * 2.0; // -1..+1 for impostor
+  o.selected = in_.selected;
   return o;
 }
 
 @fragment
-fn fs(@location(0) quad: vec2<f32>) -> @location(0) vec4<f32> {
+fn fs(@location(0) quad: vec2<f32>, @location(1) selected: f32) -> @location(0) vec4<f32> {
   let r2 = dot(quad, quad);
   if (r2 > 1.0) {
     discard;
@@ -50,6 +53,6 @@ fn fs(@location(0) quad: vec2<f32>) -> @location(0) vec4<f32> {
   let n = normalize(vec3(quad, z));
   let l = normalize(vec3(0.4, 0.6, 1.0));
   let diff = dot(n, l) * 0.5 + 0.5;
-  let col = vec3(0.95) * diff;
+  let col = mix(vec3(0.95), vec3(1.0, 0.7, 0.2), selected) * diff;
   return vec4(col, 1.0);
 }
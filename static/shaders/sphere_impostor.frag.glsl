@@ -1,5 +1,6 @@
 #version 300 es
 precision highp float;
+in float v_selected;
 out vec4 outColor;
 void main(){
   vec2 p = gl_PointCoord*2.0 - 1.0;
@@ -8,5 +9,5 @@ void main(){
   float z = sqrt(max(0.0, 1.0 - r2));
   vec3 n = normalize(vec3(p, z));
   float l = dot(n, normalize(vec3(0.4,0.6,1.0)))*0.5 + 0.5;
-  outColor = vec4(vec3(0.95)*l, 1.0);
+  outColor = vec4(mix(vec3(0.95), vec3(1.0,0.7,0.2), v_selected)*l, 1.0);
 }
@@ -1,9 +1,12 @@
 #version 300 es
 precision highp float;
 layout(location=0) in vec3 a_center;
+layout(location=1) in float a_selected;
+out float v_selected;
 uniform mat4 u_view, u_proj;
 uniform float u_pointSizePx;
 void main(){
   gl_Position = u_proj * u_view * vec4(a_center, 1.0);
   gl_PointSize = u_pointSizePx; // WebGL2: always on, no enable needed
+  v_selected = a_selected;
 }